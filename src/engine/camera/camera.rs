@@ -7,25 +7,122 @@ use std::{
 
 use wgpu::util::DeviceExt;
 use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, WindowEvent},
     keyboard::{KeyCode, PhysicalKey},
 };
 
-use crate::{engine::primitives::vector::Vector3, event::input::context::Context2D};
+use crate::{
+    engine::{
+        layer::layer::LayerID,
+        primitives::{
+            rect::Rect,
+            vector::{Vector2, Vector3},
+        },
+    },
+    event::input::context::Context2D,
+};
 
 pub struct Camera2D {
     look_at: glam::Mat4,
     proj: glam::Mat4,
     position: Vector3<f32>,
-    _near: f32,
-    _far: f32,
-    _fov_deg: f32,
+    near: f32,
+    far: f32,
+    projection: CameraProjection,
+    // Size of the whole render target, the viewport is a region of it
+    target_size: PhysicalSize<u32>,
+    viewport: Rect<f32>,
+    visible_layers: Option<HashSet<LayerID>>,
+    resize_policy: ResizePolicy,
+    // Target size the camera was designed for, kept for stretch and letterbox
+    reference_size: PhysicalSize<u32>,
+    aspect_ratio: f32,
+    zoom: f32,
+    roll: f32,
     bind_group: wgpu::BindGroup,
     bind_group_layout: wgpu::BindGroupLayout,
     buffer: wgpu::Buffer,
     key_codes: HashMap<CameraAction, KeyCode>,
     current_actions: HashSet<CameraAction>,
     speed: f32,
+    follow: CameraFollow,
+    bounds: Option<Rect<f32>>,
+    last_target: Option<glam::Vec2>,
+    look_ahead: glam::Vec2,
+    shake: CameraShake,
+    trauma: f32,
+    shake_time: f32,
+    // x, y and roll (radians) offsets, only applied to what is rendered
+    shake_offset: glam::Vec3,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CameraProjection {
+    /// Visible area depends on the camera's distance (position.z) and the field of view
+    Perspective { fov_deg: f32 },
+    /// `world_height` world units fit the height of the viewport at a zoom of 1.0,
+    /// regardless of position.z. Useful for pixel exact placement and UI.
+    Orthographic { world_height: f32 },
+}
+
+/// How a camera adapts when its render target changes size, eg the window is resized.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ResizePolicy {
+    /// Keep the original aspect ratio, stretching the image to fill the viewport
+    Stretch,
+    /// Keep the original aspect ratio, leaving bars at the sides or top and bottom
+    Letterbox,
+    /// Show more or less of the world, the visible height stays the same
+    #[default]
+    Expand,
+}
+
+/// Settings for `Camera2DSystem::follow`.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraFollow {
+    /// Roughly how many seconds the camera takes to catch up with the target,
+    /// 0.0 keeps the target exactly in place.
+    pub damping: f32,
+    /// Width and height of a box around the centre of the view that the target
+    /// can move within without moving the camera.
+    pub dead_zone: Vector2<f32>,
+    /// How far ahead of the target to look, in seconds of its current velocity.
+    pub look_ahead: f32,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            damping: 0.15,
+            dead_zone: Vector2::new(0.0, 0.0),
+            look_ahead: 0.0,
+        }
+    }
+}
+
+/// Settings for trauma based camera shake, see `Camera2DSystem::add_trauma`.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraShake {
+    /// Largest positional offset in world units, reached at full trauma
+    pub max_offset: Vector2<f32>,
+    /// Largest rotation in degrees, reached at full trauma
+    pub max_roll: f32,
+    /// How quickly the shake changes direction, in Hz
+    pub frequency: f32,
+    /// Trauma lost per second
+    pub decay: f32,
+}
+
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self {
+            max_offset: Vector2::new(0.3, 0.3),
+            max_roll: 5.0,
+            frequency: 15.0,
+            decay: 1.0,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
@@ -39,11 +136,32 @@ pub enum CameraAction {
 }
 
 impl Camera2D {
-    pub fn new(device: &wgpu::Device, fov_deg: f32, aspect_ratio: f32, speed: f32) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        fov_deg: f32,
+        target_size: PhysicalSize<u32>,
+        speed: f32,
+    ) -> Self {
+        Camera2D::with_projection(
+            device,
+            CameraProjection::Perspective { fov_deg },
+            target_size,
+            speed,
+        )
+    }
+
+    pub fn with_projection(
+        device: &wgpu::Device,
+        projection: CameraProjection,
+        target_size: PhysicalSize<u32>,
+        speed: f32,
+    ) -> Self {
+        let viewport = Rect::new(0.0, 0.0, 1.0, 1.0);
+        let aspect_ratio = Camera2DSystem::aspect_ratio(target_size, viewport);
         let near = 0.01;
         let far = 100.0;
-        let fov_rad = fov_deg.to_radians();
-        let proj = glam::Mat4::perspective_rh(fov_rad, aspect_ratio, near, far);
+        let zoom = 1.0;
+        let proj = Camera2DSystem::projection_matrix(projection, aspect_ratio, zoom, near, far);
         let look_at = glam::Mat4::look_at_rh(
             glam::Vec3::new(0.0f32, 0.0, 1.0),
             glam::Vec3::new(0.0, 0.0, 0.0),
@@ -84,9 +202,17 @@ impl Camera2D {
         Self {
             proj,
             look_at,
-            _near: near,
-            _far: far,
-            _fov_deg: fov_deg,
+            near,
+            far,
+            projection,
+            target_size,
+            viewport,
+            visible_layers: None,
+            resize_policy: ResizePolicy::default(),
+            reference_size: target_size,
+            aspect_ratio,
+            zoom,
+            roll: 0.0,
             buffer,
             bind_group,
             bind_group_layout,
@@ -94,6 +220,14 @@ impl Camera2D {
             speed,
             current_actions,
             position,
+            follow: CameraFollow::default(),
+            bounds: None,
+            last_target: None,
+            look_ahead: glam::Vec2::ZERO,
+            shake: CameraShake::default(),
+            trauma: 0.0,
+            shake_time: 0.0,
+            shake_offset: glam::Vec3::ZERO,
         }
     }
 
@@ -116,6 +250,120 @@ impl Camera2D {
     pub fn position(&self) -> Vector3<f32> {
         self.position
     }
+
+    pub fn projection(&self) -> CameraProjection {
+        self.projection
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Rotation of the camera around its view direction, in degrees.
+    pub fn roll(&self) -> f32 {
+        self.roll.to_degrees()
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.aspect_ratio
+    }
+
+    pub fn target_size(&self) -> PhysicalSize<u32> {
+        self.target_size
+    }
+
+    /// Region of the render target drawn to, (0, 0) top left and (1, 1) bottom right.
+    pub fn viewport(&self) -> Rect<f32> {
+        self.viewport
+    }
+
+    /// The viewport in physical pixels, x and y being its top left corner.
+    /// When letterboxed this is the area inside the bars.
+    pub fn viewport_pixels(&self) -> Rect<f32> {
+        self.viewport_pixels_in(self.target_size)
+    }
+
+    /// The viewport in physical pixels within a target of the given size, which may differ
+    /// from `target_size` until the camera is next updated.
+    pub fn viewport_pixels_in(&self, target_size: PhysicalSize<u32>) -> Rect<f32> {
+        let width = target_size.width as f32;
+        let height = target_size.height as f32;
+        let region = Rect::new(
+            self.viewport.x * width,
+            self.viewport.y * height,
+            self.viewport.width * width,
+            self.viewport.height * height,
+        );
+        if self.resize_policy != ResizePolicy::Letterbox {
+            return region;
+        }
+        let aspect_ratio = Camera2DSystem::aspect_ratio(self.reference_size, self.viewport);
+        let fitted_width = region.width.min(region.height * aspect_ratio);
+        let fitted_height = region.height.min(region.width / aspect_ratio);
+        Rect::new(
+            region.x + (region.width - fitted_width) / 2.0,
+            region.y + (region.height - fitted_height) / 2.0,
+            fitted_width,
+            fitted_height,
+        )
+    }
+
+    pub fn viewport_size(&self) -> PhysicalSize<u32> {
+        let pixels = self.viewport_pixels();
+        PhysicalSize::new(pixels.width.round() as u32, pixels.height.round() as u32)
+    }
+
+    pub fn resize_policy(&self) -> ResizePolicy {
+        self.resize_policy
+    }
+
+    pub fn reference_size(&self) -> PhysicalSize<u32> {
+        self.reference_size
+    }
+
+    /// Whether the camera draws the given layer, all layers are drawn by default.
+    pub fn is_layer_visible(&self, layer: LayerID) -> bool {
+        match self.visible_layers.as_ref() {
+            Some(visible_layers) => visible_layers.contains(&layer),
+            None => true,
+        }
+    }
+
+    pub fn follow_settings(&self) -> CameraFollow {
+        self.follow
+    }
+
+    pub fn bounds(&self) -> Option<Rect<f32>> {
+        self.bounds
+    }
+
+    pub fn shake_settings(&self) -> CameraShake {
+        self.shake
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Half the width and height of the world visible on the plane z = 0,
+    /// measured along the camera's own axes.
+    pub fn visible_half_extents(&self) -> Vector2<f32> {
+        let half_height = self.unzoomed_half_height() / self.zoom;
+        Vector2::new(half_height * self.aspect_ratio, half_height)
+    }
+
+    fn unzoomed_half_height(&self) -> f32 {
+        match self.projection {
+            CameraProjection::Perspective { fov_deg } => {
+                self.position.z * (fov_deg.to_radians() / 2.0).tan()
+            }
+            CameraProjection::Orthographic { world_height } => world_height / 2.0,
+        }
+    }
+
+    fn view_projection(&self) -> glam::Mat4 {
+        self.proj * self.look_at
+    }
 }
 
 pub struct Camera2DSystem;
@@ -123,19 +371,335 @@ pub struct Camera2DSystem;
 impl Camera2DSystem {
     pub fn transform(camera: &mut Camera2D, position: Vector3<f32>) {
         camera.position = position;
-        camera.look_at = glam::Mat4::look_at_rh(
+        Camera2DSystem::update_view(camera);
+    }
+
+    pub fn set_speed(camera: &mut Camera2D, speed: f32) {
+        camera.speed = speed;
+    }
+
+    /// Switches between perspective and orthographic projection.
+    pub fn set_projection(camera: &mut Camera2D, projection: CameraProjection) {
+        camera.projection = projection;
+        Camera2DSystem::update_projection(camera);
+    }
+
+    /// Scale factor applied on top of the projection, 2.0 shows half as much of the world.
+    /// Perspective cameras can also zoom by moving along z.
+    pub fn set_zoom(camera: &mut Camera2D, zoom: f32) {
+        camera.zoom = zoom.max(f32::EPSILON);
+        Camera2DSystem::update_projection(camera);
+    }
+
+    /// Rotates the camera around its view direction, the world appears to turn the other way.
+    pub fn set_roll(camera: &mut Camera2D, degrees: f32) {
+        camera.roll = (degrees % 360.0).to_radians();
+        Camera2DSystem::update_view(camera);
+    }
+
+    /// Centres the camera on a world space rectangle, x and y being its bottom left corner,
+    /// and zooms so all of it is visible with `padding` world units to spare on each side.
+    /// Handy for keeping every player on screen in local co-op.
+    pub fn zoom_to_fit(camera: &mut Camera2D, rect: Rect<f32>, padding: f32) {
+        let (sin, cos) = camera.roll.sin_cos();
+        // Size of the rectangle along the camera's rotated axes
+        let width = (rect.width * cos).abs() + (rect.height * sin).abs() + padding * 2.0;
+        let height = (rect.width * sin).abs() + (rect.height * cos).abs() + padding * 2.0;
+        camera.position.x = rect.x + rect.width / 2.0;
+        camera.position.y = rect.y + rect.height / 2.0;
+        let half_height = camera.unzoomed_half_height();
+        let half_width = half_height * camera.aspect_ratio;
+        if width > 0.0 && height > 0.0 {
+            camera.zoom = (half_width * 2.0 / width).min(half_height * 2.0 / height);
+        }
+        Camera2DSystem::update_view(camera);
+        Camera2DSystem::update_projection(camera);
+    }
+
+    pub fn set_follow_settings(camera: &mut Camera2D, follow: CameraFollow) {
+        camera.follow = follow;
+    }
+
+    /// Keeps the visible area inside a world space rectangle when following, x and y
+    /// being its bottom left corner. If the view is larger than the bounds it is centred on them.
+    pub fn set_bounds(camera: &mut Camera2D, bounds: Option<Rect<f32>>) {
+        camera.bounds = bounds;
+    }
+
+    /// Moves the camera towards a target, call once per frame.
+    /// See `CameraFollow` for how the movement can be tuned.
+    pub fn follow(camera: &mut Camera2D, target: Vector3<f32>, delta_time: Duration) {
+        let dt = delta_time.as_secs_f32();
+        let target = glam::Vec2::new(target.x, target.y);
+        let smoothing = |time: f32| {
+            if time <= 0.0 {
+                1.0
+            } else {
+                1.0 - (-dt / time).exp()
+            }
+        };
+
+        let velocity = match camera.last_target {
+            Some(last) if dt > 0.0 => (target - last) / dt,
+            _ => glam::Vec2::ZERO,
+        };
+        camera.last_target = Some(target);
+        let look_ahead = velocity * camera.follow.look_ahead;
+        camera.look_ahead += (look_ahead - camera.look_ahead) * smoothing(camera.follow.damping);
+        let focus = target + camera.look_ahead;
+
+        // Only move far enough to bring the focus back to the edge of the dead zone
+        let centre = glam::Vec2::new(camera.position.x, camera.position.y);
+        let half_dead_zone =
+            glam::Vec2::new(camera.follow.dead_zone.x, camera.follow.dead_zone.y) / 2.0;
+        let offset = focus - centre;
+        let desired = centre + offset - offset.clamp(-half_dead_zone, half_dead_zone);
+
+        let mut centre = centre + (desired - centre) * smoothing(camera.follow.damping);
+        if let Some(bounds) = camera.bounds {
+            let half_extents = camera.visible_half_extents();
+            let clamp_axis = |value: f32, start: f32, length: f32, half_extent: f32| {
+                if length <= half_extent * 2.0 {
+                    start + length / 2.0
+                } else {
+                    value.clamp(start + half_extent, start + length - half_extent)
+                }
+            };
+            centre.x = clamp_axis(centre.x, bounds.x, bounds.width, half_extents.x);
+            centre.y = clamp_axis(centre.y, bounds.y, bounds.height, half_extents.y);
+        }
+        camera.position.x = centre.x;
+        camera.position.y = centre.y;
+        Camera2DSystem::update_view(camera);
+    }
+
+    /// Forgets the target's previous position, call when the target teleports
+    /// so the jump does not count as velocity for look ahead.
+    pub fn reset_follow(camera: &mut Camera2D) {
+        camera.last_target = None;
+        camera.look_ahead = glam::Vec2::ZERO;
+    }
+
+    pub fn set_shake_settings(camera: &mut Camera2D, shake: CameraShake) {
+        camera.shake = shake;
+    }
+
+    /// Adds trauma between 0.0 and 1.0, eg 0.3 for a hit and 0.8 for an explosion.
+    /// Shake grows with the square of trauma, so small amounts stay subtle.
+    pub fn add_trauma(camera: &mut Camera2D, trauma: f32) {
+        camera.trauma = (camera.trauma + trauma).clamp(0.0, 1.0);
+    }
+
+    /// Advances the shake and decays trauma, call once per frame before `update`.
+    /// The camera's position is left untouched.
+    pub fn shake(camera: &mut Camera2D, delta_time: Duration) {
+        let dt = delta_time.as_secs_f32();
+        camera.trauma = (camera.trauma - camera.shake.decay * dt).max(0.0);
+        if camera.trauma == 0.0 {
+            camera.shake_offset = glam::Vec3::ZERO;
+            return;
+        }
+        camera.shake_time += dt;
+        let t = camera.shake_time * camera.shake.frequency;
+        let intensity = camera.trauma * camera.trauma;
+        camera.shake_offset = glam::Vec3::new(
+            camera.shake.max_offset.x * intensity * Camera2DSystem::noise(t, 0),
+            camera.shake.max_offset.y * intensity * Camera2DSystem::noise(t, 1),
+            camera.shake.max_roll.to_radians() * intensity * Camera2DSystem::noise(t, 2),
+        );
+    }
+
+    /// Sets the size in pixels of the whole render target, eg after the window is resized.
+    /// The projection is updated according to the camera's resize policy.
+    pub fn set_target_size(camera: &mut Camera2D, target_size: PhysicalSize<u32>) {
+        camera.target_size = target_size;
+        Camera2DSystem::update_aspect_ratio(camera);
+    }
+
+    pub fn set_resize_policy(camera: &mut Camera2D, resize_policy: ResizePolicy) {
+        camera.resize_policy = resize_policy;
+        Camera2DSystem::update_aspect_ratio(camera);
+    }
+
+    /// The target size whose aspect ratio is kept by the stretch and letterbox policies,
+    /// defaults to the target size when the camera was created.
+    pub fn set_reference_size(camera: &mut Camera2D, reference_size: PhysicalSize<u32>) {
+        camera.reference_size = reference_size;
+        Camera2DSystem::update_aspect_ratio(camera);
+    }
+
+    /// Restricts the camera to a region of the render target, (0, 0) being the top left
+    /// and (1, 1) the bottom right. Eg (0, 0, 0.5, 1) for the left half in split screen.
+    pub fn set_viewport(camera: &mut Camera2D, viewport: Rect<f32>) {
+        camera.viewport = viewport;
+        Camera2DSystem::update_aspect_ratio(camera);
+    }
+
+    /// Only draw the given layers with this camera, None draws every layer.
+    pub fn set_visible_layers(camera: &mut Camera2D, layers: Option<&[LayerID]>) {
+        camera.visible_layers = layers.map(|layers| layers.iter().copied().collect());
+    }
+
+    /// Whether a position in physical pixels of the render target is inside the camera's
+    /// viewport, eg to find which split screen view the mouse is over.
+    pub fn viewport_contains(camera: &Camera2D, screen: PhysicalPosition<f64>) -> bool {
+        let pixels = camera.viewport_pixels();
+        let (x, y) = (screen.x as f32, screen.y as f32);
+        x >= pixels.x
+            && x < pixels.x + pixels.width
+            && y >= pixels.y
+            && y < pixels.y + pixels.height
+    }
+
+    /// Converts a position in physical pixels of the render target to the point in the world
+    /// under it on the plane z = 0. For the mouse, map `Context2D::mouse_position` with
+    /// `EffectSystem::window_to_render_position` first, which returns None over the bars
+    /// around a virtual resolution where there is no world to hit.
+    pub fn screen_to_world(camera: &Camera2D, screen: PhysicalPosition<f64>) -> Vector3<f32> {
+        Camera2DSystem::screen_to_world_at(camera, screen, 0.0)
+    }
+
+    /// Same as `screen_to_world`, for entities placed at a different z.
+    pub fn screen_to_world_at(
+        camera: &Camera2D,
+        screen: PhysicalPosition<f64>,
+        z: f32,
+    ) -> Vector3<f32> {
+        let pixels = camera.viewport_pixels();
+        let width = pixels.width.max(1.0);
+        let height = pixels.height.max(1.0);
+        let ndc_x = 2.0 * (screen.x as f32 - pixels.x) / width - 1.0;
+        let ndc_y = 1.0 - 2.0 * (screen.y as f32 - pixels.y) / height;
+        // Cast a ray from the near plane to the far plane and find where it crosses z
+        let inverse = camera.view_projection().inverse();
+        let near = inverse.project_point3(glam::Vec3::new(ndc_x, ndc_y, 0.0));
+        let far = inverse.project_point3(glam::Vec3::new(ndc_x, ndc_y, 1.0));
+        let direction = far - near;
+        let t = if direction.z.abs() > f32::EPSILON {
+            (z - near.z) / direction.z
+        } else {
+            0.0
+        };
+        let world = near + direction * t;
+        Vector3::new(world.x, world.y, z)
+    }
+
+    /// Converts a world position to physical pixels on the render target.
+    /// Returns None for points behind a perspective camera.
+    pub fn world_to_screen(
+        camera: &Camera2D,
+        world: Vector3<f32>,
+    ) -> Option<PhysicalPosition<f64>> {
+        let clip = camera.view_projection() * glam::Vec4::new(world.x, world.y, world.z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        let pixels = camera.viewport_pixels();
+        let x = pixels.x + (ndc.x + 1.0) / 2.0 * pixels.width;
+        let y = pixels.y + (1.0 - ndc.y) / 2.0 * pixels.height;
+        Some(PhysicalPosition::new(x as f64, y as f64))
+    }
+
+    fn update_view(camera: &mut Camera2D) {
+        let position = camera.position;
+        camera.look_at = Camera2DSystem::view_matrix(
             glam::Vec3::new(position.x, position.y, position.z),
+            camera.roll,
+        );
+    }
+
+    fn view_matrix(position: glam::Vec3, roll: f32) -> glam::Mat4 {
+        let (sin, cos) = roll.sin_cos();
+        glam::Mat4::look_at_rh(
+            position,
             glam::Vec3::new(position.x, position.y, 0.0),
-            glam::Vec3::Y,
+            glam::Vec3::new(-sin, cos, 0.0),
+        )
+    }
+
+    // Smooth 1D value noise between -1.0 and 1.0, each seed gives an unrelated curve
+    fn noise(t: f32, seed: u32) -> f32 {
+        let hash = |i: i32| {
+            let mut x = (i as u32).wrapping_mul(0x9E37_79B1) ^ seed.wrapping_mul(0x85EB_CA77);
+            x ^= x >> 15;
+            x = x.wrapping_mul(0x2C1B_3C6D);
+            x ^= x >> 12;
+            x = x.wrapping_mul(0x297A_2D39);
+            x ^= x >> 15;
+            x as f32 / u32::MAX as f32 * 2.0 - 1.0
+        };
+        let i = t.floor();
+        let f = t - i;
+        let (a, b) = (hash(i as i32), hash(i as i32 + 1));
+        a + (b - a) * f * f * (3.0 - 2.0 * f)
+    }
+
+    fn update_aspect_ratio(camera: &mut Camera2D) {
+        let size = match camera.resize_policy {
+            ResizePolicy::Expand => camera.target_size,
+            ResizePolicy::Stretch | ResizePolicy::Letterbox => camera.reference_size,
+        };
+        camera.aspect_ratio = Camera2DSystem::aspect_ratio(size, camera.viewport);
+        Camera2DSystem::update_projection(camera);
+    }
+
+    fn aspect_ratio(target_size: PhysicalSize<u32>, viewport: Rect<f32>) -> f32 {
+        let width = target_size.width as f32 * viewport.width;
+        let height = target_size.height as f32 * viewport.height;
+        width.max(1.0) / height.max(1.0)
+    }
+
+    fn update_projection(camera: &mut Camera2D) {
+        camera.proj = Camera2DSystem::projection_matrix(
+            camera.projection,
+            camera.aspect_ratio,
+            camera.zoom,
+            camera.near,
+            camera.far,
         );
     }
 
-    pub fn set_speed(camera: &mut Camera2D, speed: f32) {
-        camera.speed = speed;
+    fn projection_matrix(
+        projection: CameraProjection,
+        aspect_ratio: f32,
+        zoom: f32,
+        near: f32,
+        far: f32,
+    ) -> glam::Mat4 {
+        match projection {
+            CameraProjection::Perspective { fov_deg } => {
+                glam::Mat4::from_scale(glam::Vec3::new(zoom, zoom, 1.0))
+                    * glam::Mat4::perspective_rh(fov_deg.to_radians(), aspect_ratio, near, far)
+            }
+            CameraProjection::Orthographic { world_height } => {
+                let half_height = world_height / zoom / 2.0;
+                let half_width = half_height * aspect_ratio;
+                glam::Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        }
     }
 
+    /// Uploads the camera to the GPU, with any shake applied on top of its position.
     pub fn update(camera: &mut Camera2D, queue: &wgpu::Queue) {
-        let comp = camera.proj * camera.look_at;
+        let view = if camera.shake_offset == glam::Vec3::ZERO {
+            camera.look_at
+        } else {
+            let position = camera.position;
+            let offset = camera.shake_offset;
+            Camera2DSystem::view_matrix(
+                glam::Vec3::new(position.x + offset.x, position.y + offset.y, position.z),
+                camera.roll + offset.z,
+            )
+        };
+        let comp = camera.proj * view;
         queue.write_buffer(
             &camera.buffer,
             0,
@@ -167,20 +731,26 @@ impl Camera2DSystem {
                 CameraAction::Left => {
                     camera.position.x -= camera.speed * dt;
                 }
-                CameraAction::ZoomIn => {
-                    camera.position.z -= camera.speed * dt;
-                }
-                CameraAction::ZoomOut => {
-                    camera.position.z += camera.speed * dt;
-                }
+                CameraAction::ZoomIn => match camera.projection {
+                    CameraProjection::Perspective { .. } => {
+                        camera.position.z -= camera.speed * dt;
+                    }
+                    CameraProjection::Orthographic { .. } => {
+                        camera.zoom *= 1.0 + camera.speed * dt;
+                    }
+                },
+                CameraAction::ZoomOut => match camera.projection {
+                    CameraProjection::Perspective { .. } => {
+                        camera.position.z += camera.speed * dt;
+                    }
+                    CameraProjection::Orthographic { .. } => {
+                        camera.zoom /= 1.0 + camera.speed * dt;
+                    }
+                },
             }
         }
-
-        camera.look_at = glam::Mat4::look_at_rh(
-            glam::Vec3::new(camera.position.x, camera.position.y, camera.position.z),
-            glam::Vec3::new(camera.position.x, camera.position.y, 0.0),
-            glam::Vec3::Y,
-        );
+        Camera2DSystem::update_projection(camera);
+        Camera2DSystem::update_view(camera);
     }
 
     pub fn set_inputs(camera: &mut Camera2D, inputs: &[(CameraAction, KeyCode)]) {
@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use image::RgbaImage;
+
+struct CaptureSequence {
+    interval: u32,
+    directory: PathBuf,
+    frames_seen: u64,
+    saved: u32,
+}
+
+// Frames are only read back from the GPU when something asked for them,
+// so frames that are not captured cost nothing extra.
+#[derive(Default)]
+pub struct FrameCapture {
+    keep_next: bool,
+    screenshots: Vec<PathBuf>,
+    sequence: Option<CaptureSequence>,
+    sequence_due: bool,
+    captured: Option<RgbaImage>,
+    error: Option<anyhow::Error>,
+}
+
+impl FrameCapture {
+    pub fn new() -> Self {
+        Self {
+            keep_next: false,
+            screenshots: Vec::new(),
+            sequence: None,
+            sequence_due: false,
+            captured: None,
+            error: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sequence.is_some()
+    }
+}
+
+pub struct FrameCaptureSystem;
+
+impl FrameCaptureSystem {
+    /// Keep a copy of the next rendered frame, retrieve it with `take_frame`.
+    pub fn capture_next_frame(capture: &mut FrameCapture) {
+        capture.keep_next = true;
+    }
+
+    /// Save the next rendered frame as a PNG at the given path.
+    pub fn save_next_frame(capture: &mut FrameCapture, path: impl Into<PathBuf>) {
+        capture.screenshots.push(path.into());
+    }
+
+    /// Save every `interval`th frame to `directory` as frame_00000.png, frame_00001.png etc.
+    /// The directory is created if it does not exist. Any running sequence is replaced.
+    pub fn start_sequence(
+        capture: &mut FrameCapture,
+        interval: u32,
+        directory: impl Into<PathBuf>,
+    ) -> Result<()> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        capture.sequence = Some(CaptureSequence {
+            interval: interval.max(1),
+            directory,
+            frames_seen: 0,
+            saved: 0,
+        });
+        Ok(())
+    }
+
+    pub fn stop_sequence(capture: &mut FrameCapture) {
+        capture.sequence = None;
+    }
+
+    pub fn take_frame(capture: &mut FrameCapture) -> Option<RgbaImage> {
+        capture.captured.take()
+    }
+
+    /// Capturing happens during rendering, so failures are kept here rather than
+    /// failing the frame. Only the most recent error is kept.
+    pub fn take_error(capture: &mut FrameCapture) -> Option<anyhow::Error> {
+        capture.error.take()
+    }
+
+    /// Call once per rendered frame, returns whether the frame should be read back.
+    pub fn begin_frame(capture: &mut FrameCapture) -> bool {
+        capture.sequence_due = match capture.sequence.as_mut() {
+            Some(sequence) => {
+                let due = sequence.frames_seen % sequence.interval as u64 == 0;
+                sequence.frames_seen += 1;
+                due
+            }
+            None => false,
+        };
+        capture.sequence_due || capture.keep_next || !capture.screenshots.is_empty()
+    }
+
+    /// Hands the read back frame to whatever requested it.
+    pub fn end_frame(capture: &mut FrameCapture, frame: Result<RgbaImage>) {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                capture.error = Some(e);
+                return;
+            }
+        };
+        for path in capture.screenshots.drain(..) {
+            if let Err(e) = frame.save(&path) {
+                capture.error = Some(e.into());
+            }
+        }
+        if capture.sequence_due {
+            if let Some(sequence) = capture.sequence.as_mut() {
+                let path = sequence
+                    .directory
+                    .join(format!("frame_{:05}.png", sequence.saved));
+                sequence.saved += 1;
+                if let Err(e) = frame.save(path) {
+                    capture.error = Some(e.into());
+                }
+            }
+        }
+        if capture.keep_next {
+            capture.keep_next = false;
+            capture.captured = Some(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("effect_capture_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn sequence_captures_every_interval() {
+        let mut capture = FrameCapture::new();
+        let directory = temp_directory("interval");
+        FrameCaptureSystem::start_sequence(&mut capture, 3, &directory).unwrap();
+        let due = (0..7)
+            .map(|_| FrameCaptureSystem::begin_frame(&mut capture))
+            .collect::<Vec<_>>();
+        assert_eq!(due, [true, false, false, true, false, false, true]);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn sequence_numbers_saved_frames() {
+        let mut capture = FrameCapture::new();
+        let directory = temp_directory("numbering");
+        FrameCaptureSystem::start_sequence(&mut capture, 2, &directory).unwrap();
+        for _ in 0..5 {
+            if FrameCaptureSystem::begin_frame(&mut capture) {
+                FrameCaptureSystem::end_frame(&mut capture, Ok(RgbaImage::new(2, 2)));
+            }
+        }
+        assert!(FrameCaptureSystem::take_error(&mut capture).is_none());
+        let mut files = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            ["frame_00000.png", "frame_00001.png", "frame_00002.png"]
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn failed_readback_keeps_screenshots_queued() {
+        let mut capture = FrameCapture::new();
+        let directory = temp_directory("failed");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("shot.png");
+        FrameCaptureSystem::save_next_frame(&mut capture, &path);
+        assert!(FrameCaptureSystem::begin_frame(&mut capture));
+        FrameCaptureSystem::end_frame(&mut capture, Err(anyhow::anyhow!("readback failed")));
+        assert!(FrameCaptureSystem::take_error(&mut capture).is_some());
+        assert!(!path.exists());
+
+        assert!(FrameCaptureSystem::begin_frame(&mut capture));
+        FrameCaptureSystem::end_frame(&mut capture, Ok(RgbaImage::new(2, 2)));
+        assert!(path.exists());
+        assert!(!FrameCaptureSystem::begin_frame(&mut capture));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn captured_frame_is_taken_once() {
+        let mut capture = FrameCapture::new();
+        assert!(!FrameCaptureSystem::begin_frame(&mut capture));
+        FrameCaptureSystem::capture_next_frame(&mut capture);
+        assert!(FrameCaptureSystem::begin_frame(&mut capture));
+        FrameCaptureSystem::end_frame(&mut capture, Ok(RgbaImage::new(4, 3)));
+        assert_eq!(
+            FrameCaptureSystem::take_frame(&mut capture).map(|frame| frame.dimensions()),
+            Some((4, 3))
+        );
+        assert!(FrameCaptureSystem::take_frame(&mut capture).is_none());
+        assert!(!FrameCaptureSystem::begin_frame(&mut capture));
+    }
+}
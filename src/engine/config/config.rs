@@ -0,0 +1,202 @@
+use std::path::PathBuf;
+
+use winit::{
+    dpi::PhysicalSize,
+    monitor::MonitorHandle,
+    window::{Fullscreen, Icon},
+};
+
+use crate::engine::util::effect_error::EngineError;
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Borderless window covering the monitor, quick to switch in and out of
+    Borderless,
+    /// Takes over the monitor using its highest resolution and refresh rate
+    Fullscreen,
+}
+
+impl WindowMode {
+    /// The winit fullscreen setting for this mode on the given monitor.
+    pub fn to_fullscreen(self, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+        match self {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            WindowMode::Fullscreen => {
+                let video_mode = monitor?.video_modes().max_by_key(|mode| {
+                    (
+                        mode.size().width * mode.size().height,
+                        mode.refresh_rate_millihertz(),
+                    )
+                })?;
+                Some(Fullscreen::Exclusive(video_mode))
+            }
+        }
+    }
+}
+
+/// Settings used to create the window and engine, passed to `init_engine`.
+/// Start from `EngineConfig::new()` and change what you need with the `with_` methods.
+#[derive(Debug, Clone)]
+pub struct EngineConfig {
+    pub title: String,
+    pub dimensions: PhysicalSize<u32>,
+    pub icon: Option<PathBuf>,
+    pub resizable: bool,
+    pub window_mode: WindowMode,
+    pub decorations: bool,
+    pub present_mode: wgpu::PresentMode,
+    /// Samples per pixel, 1 turns multisampling off. Lowered to what the adapter supports.
+    pub msaa_samples: u32,
+    /// Opaque texels with a higher z are drawn on top, across layers too. Partly transparent
+    /// texels, eg faded sprites and soft edges, don't hide anything drawn after them, so they
+    /// only blend correctly within a layer using `SortMode::Z`, and across layers only when
+    /// the layer order matches z.
+    pub depth_buffer: bool,
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    /// Use an adapter whose name contains this, eg "nvidia". Case insensitive.
+    /// Software adapters can be named without `allow_software_fallback`.
+    /// Init fails with `EngineError::AdapterNotFound` if none match.
+    pub adapter_name: Option<String>,
+    /// Allow software rendering when no GPU is available. Slow, but better than nothing.
+    pub allow_software_fallback: bool,
+    /// Features the game needs beyond what the engine uses, init fails without them.
+    pub required_features: wgpu::Features,
+    pub clear_colour: wgpu::Color,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("Effect Engine"),
+            dimensions: PhysicalSize::new(800, 600),
+            icon: None,
+            resizable: true,
+            window_mode: WindowMode::Windowed,
+            decorations: true,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            msaa_samples: 1,
+            depth_buffer: false,
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::default(),
+            adapter_name: None,
+            allow_software_fallback: false,
+            required_features: wgpu::Features::empty(),
+            clear_colour: wgpu::Color {
+                r: 0.0,
+                g: 0.5,
+                b: 0.5,
+                a: 1.0,
+            },
+        }
+    }
+}
+
+impl EngineConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn with_dimensions(mut self, dimensions: PhysicalSize<u32>) -> Self {
+        self.dimensions = dimensions;
+        self
+    }
+
+    /// Path to an image used as the window icon.
+    pub fn with_icon(mut self, path: impl Into<PathBuf>) -> Self {
+        self.icon = Some(path.into());
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn with_window_mode(mut self, window_mode: WindowMode) -> Self {
+        self.window_mode = window_mode;
+        self
+    }
+
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    /// Shorthand for `with_present_mode` using AutoVsync or AutoNoVsync.
+    pub fn with_v_sync(self, v_sync: bool) -> Self {
+        match v_sync {
+            true => self.with_present_mode(wgpu::PresentMode::AutoVsync),
+            false => self.with_present_mode(wgpu::PresentMode::AutoNoVsync),
+        }
+    }
+
+    pub fn with_msaa_samples(mut self, msaa_samples: u32) -> Self {
+        self.msaa_samples = msaa_samples.max(1);
+        self
+    }
+
+    pub fn with_depth_buffer(mut self, depth_buffer: bool) -> Self {
+        self.depth_buffer = depth_buffer;
+        self
+    }
+
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    pub fn with_adapter_name(mut self, adapter_name: impl Into<String>) -> Self {
+        self.adapter_name = Some(adapter_name.into());
+        self
+    }
+
+    pub fn with_software_fallback(mut self, allow_software_fallback: bool) -> Self {
+        self.allow_software_fallback = allow_software_fallback;
+        self
+    }
+
+    pub fn with_required_features(mut self, required_features: wgpu::Features) -> Self {
+        self.required_features = required_features;
+        self
+    }
+
+    /// Colour behind everything drawn. Use an alpha below 1.0 only when the frame is
+    /// composited over something else, eg a transparent window or a captured image.
+    pub fn with_clear_colour(mut self, clear_colour: wgpu::Color) -> Self {
+        self.clear_colour = clear_colour;
+        self
+    }
+
+    pub fn load_icon(&self) -> Result<Option<Icon>, EngineError> {
+        let path = match self.icon.as_ref() {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let image = image::open(path)
+            .map_err(|error| EngineError::InvalidIcon(error.to_string()))?
+            .into_rgba8();
+        let (width, height) = image.dimensions();
+        Icon::from_rgba(image.into_raw(), width, height)
+            .map(Some)
+            .map_err(|error| EngineError::InvalidIcon(error.to_string()))
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::engine::entity::entity::Entity2D;
@@ -8,21 +10,71 @@ use winit::dpi::PhysicalSize;
 
 use super::camera::camera::Camera2D;
 use super::camera::camera::Camera2DSystem;
+use super::camera::camera::CameraProjection;
+use super::capture::capture::{FrameCapture, FrameCaptureSystem};
+use super::config::config::{EngineConfig, WindowMode};
 use super::texture::background2d::Background2D;
+use super::upscale::upscale::{Upscaler, UpscalerSystem};
+use super::util::effect_error::{EffectError, EngineError};
+use super::util::readback::texture_to_image;
 use super::{primitives::vertex::Vertex, texture::texture2d::Texture2D};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use image::RgbaImage;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// What happened to a frame passed to `Engine::render`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameStatus {
+    Presented,
+    /// Nothing was drawn, rendering the next frame as normal is enough to recover
+    Skipped(SkipReason),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SkipReason {
+    /// The window has no area to draw to
+    Minimised,
+    /// The surface was lost, eg by a driver reset, and has been reconfigured
+    SurfaceLost,
+    /// The surface no longer matched the window and has been reconfigured
+    SurfaceOutdated,
+    /// The next surface texture took too long to become available
+    Timeout,
+}
 
 pub struct Engine {
-    surface: wgpu::Surface<'static>,
+    surface: Option<wgpu::Surface<'static>>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     surface_configuration: wgpu::SurfaceConfiguration,
-    window: Arc<winit::window::Window>,
-    render_pipeline: wgpu::RenderPipeline,
+    window: Option<Arc<winit::window::Window>>,
+    // Render target used when there is no surface to present to.
+    offscreen: Option<wgpu::Texture>,
+    capture: FrameCapture,
+    // One per blend mode, layers pick theirs when drawn
+    render_pipelines: HashMap<BlendMode, wgpu::RenderPipeline>,
+    // Draw only the opaque texels and write their depth, before render_pipelines draw
+    // everything without writing depth. Empty without a depth buffer.
+    opaque_pipelines: HashMap<BlendMode, wgpu::RenderPipeline>,
+    background_pipeline: wgpu::RenderPipeline,
+    // Clears each camera's viewport before it draws
+    clear_pipeline: wgpu::RenderPipeline,
     texture_bgl: wgpu::BindGroupLayout,
     background: Option<Background2D>,
     index_buffer: wgpu::Buffer,
+    upscaler: Upscaler,
+    msaa_samples: u32,
+    // Multisampled target resolved into the frame, None when MSAA is off
+    msaa_target: Option<wgpu::TextureView>,
+    depth_target: Option<wgpu::TextureView>,
+    clear_colour: wgpu::Color,
+    // Present modes the surface supports, for validating runtime changes
+    present_modes: Vec<wgpu::PresentMode>,
+    window_mode: WindowMode,
+    // Surface settings changed since the last frame, applied before the next one
+    surface_outdated: bool,
 }
 
 /*
@@ -31,32 +83,245 @@ pub struct Engine {
 * 0.3.0 release
 */
 impl Engine {
-    pub async fn new(window: winit::window::Window, camera_fov: f32, v_sync: bool) -> Self {
+    pub async fn new(
+        window: winit::window::Window,
+        config: &EngineConfig,
+    ) -> Result<Self, EngineError> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
+            backends: config.backends,
             ..Default::default()
         });
         let window = Arc::new(window);
-        let surface = instance.create_surface(window.clone()).unwrap();
-        let adapter = instance
+        let surface = instance
+            .create_surface(window.clone())
+            .map_err(EngineError::CreateSurface)?;
+        let adapter = Engine::select_adapter(&instance, config, Some(&surface), false).await?;
+        let (device, queue) = Engine::request_device(&adapter, config).await?;
+
+        let surface_capabilities = surface.get_capabilities(&adapter);
+        // Auto modes always fall back to something supported, the rest may not exist
+        let present_mode = match config.present_mode {
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync => config.present_mode,
+            mode if surface_capabilities.present_modes.contains(&mode) => mode,
+            _ => wgpu::PresentMode::Fifo,
+        };
+        // Textures are sRGB, so the surface should be too, matching headless rendering
+        let surface_format = surface_capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb())
+            .unwrap_or(surface_capabilities.formats[0]);
+        // Copying straight from the surface makes frame capture cheaper, when allowed.
+        let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        if surface_capabilities
+            .usages
+            .contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        let surface_configuration = wgpu::SurfaceConfiguration {
+            usage,
+            format: surface_format,
+            width: window.inner_size().width.max(1),
+            height: window.inner_size().height.max(1),
+            present_mode,
+            alpha_mode: surface_capabilities.alpha_modes[0],
+            view_formats: Vec::new(),
+            desired_maximum_frame_latency: Default::default(),
+        };
+        surface.configure(&device, &surface_configuration);
+        let msaa_samples =
+            Engine::supported_msaa_samples(&adapter, &device, surface_format, config);
+
+        let mut engine = Self::build(
+            device,
+            queue,
+            surface_configuration,
+            Some(surface),
+            Some(window),
+            msaa_samples,
+            config,
+        );
+        engine.present_modes = surface_capabilities.present_modes;
+        engine.window_mode = config.window_mode;
+        Ok(engine)
+    }
+
+    /// Creates an engine without a window or surface. Frames are rendered into
+    /// an offscreen texture of the configured dimensions, which makes it possible to render
+    /// on machines without a display, such as CI servers.
+    /// A software / fallback adapter is preferred when one is available.
+    pub async fn new_headless(config: &EngineConfig) -> Result<Self, EngineError> {
+        let dimensions = config.dimensions;
+        if dimensions.width == 0 || dimensions.height == 0 {
+            return Err(EngineError::InvalidDimensions);
+        }
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: config.backends,
+            ..Default::default()
+        });
+        let adapter = Engine::select_adapter(&instance, config, None, true).await?;
+        let (device, queue) = Engine::request_device(&adapter, config).await?;
+
+        let surface_configuration = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: dimensions.width,
+            height: dimensions.height,
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: Vec::new(),
+            desired_maximum_frame_latency: Default::default(),
+        };
+        let msaa_samples =
+            Engine::supported_msaa_samples(&adapter, &device, surface_configuration.format, config);
+
+        Ok(Self::build(
+            device,
+            queue,
+            surface_configuration,
+            None,
+            None,
+            msaa_samples,
+            config,
+        ))
+    }
+
+    /// Information on every adapter available with the given backends, eg for a settings
+    /// menu. Pass a name from here to `EngineConfig::with_adapter_name` to use it, software
+    /// adapters included, their `device_type` is `wgpu::DeviceType::Cpu`.
+    pub fn available_adapters(backends: wgpu::Backends) -> Vec<wgpu::AdapterInfo> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        instance
+            .enumerate_adapters(backends)
+            .iter()
+            .map(|adapter| adapter.get_info())
+            .collect()
+    }
+
+    // Only the named adapter if there is one, otherwise the power preference, then software
+    // if allowed.
+    // Headless rendering prefers software adapters as they give the same results everywhere.
+    async fn select_adapter(
+        instance: &wgpu::Instance,
+        config: &EngineConfig,
+        surface: Option<&wgpu::Surface<'static>>,
+        prefer_software: bool,
+    ) -> Result<wgpu::Adapter, EngineError> {
+        let allow_software = prefer_software || config.allow_software_fallback;
+        let compatible = |adapter: &wgpu::Adapter| match surface {
+            Some(surface) => adapter.is_surface_supported(surface),
+            None => true,
+        };
+        let is_software =
+            |adapter: &wgpu::Adapter| adapter.get_info().device_type == wgpu::DeviceType::Cpu;
+        let mut adapters = instance.enumerate_adapters(config.backends);
+        adapters.retain(compatible);
+
+        // Asking for an adapter by name allows software ones, as they are listed by
+        // available_adapters too
+        if let Some(name) = config.adapter_name.as_ref() {
+            let lowercase = name.to_lowercase();
+            let index = adapters
+                .iter()
+                .position(|adapter| adapter.get_info().name.to_lowercase().contains(&lowercase))
+                .ok_or_else(|| EngineError::AdapterNotFound(name.clone()))?;
+            return Ok(adapters.swap_remove(index));
+        }
+        adapters.retain(|adapter| allow_software || !is_software(adapter));
+        if prefer_software {
+            if let Some(index) = adapters.iter().position(is_software) {
+                return Ok(adapters.swap_remove(index));
+            }
+        }
+        let requested = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
+                power_preference: config.power_preference,
                 force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
+                compatible_surface: surface,
             })
             .await
-            .unwrap();
-        let (device, queue) = adapter
+            .filter(|adapter| allow_software || !is_software(adapter));
+        if let Some(adapter) = requested {
+            return Ok(adapter);
+        }
+        if allow_software {
+            let fallback = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: config.power_preference,
+                    force_fallback_adapter: true,
+                    compatible_surface: surface,
+                })
+                .await;
+            if let Some(adapter) = fallback {
+                return Ok(adapter);
+            }
+        }
+        // request_adapter can miss adapters on some platforms, take anything left over
+        adapters.into_iter().next().ok_or(EngineError::NoAdapter)
+    }
+
+    // Only what the engine needs, plus anything the user asked for, so older GPUs still work
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+        config: &EngineConfig,
+    ) -> Result<(wgpu::Device, wgpu::Queue), EngineError> {
+        let missing_features = config.required_features - adapter.features();
+        if !missing_features.is_empty() {
+            return Err(EngineError::MissingFeatures(missing_features));
+        }
+        // Allows more MSAA sample counts when available
+        let optional_features =
+            adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Adapter"),
-                    required_features: adapter.features(),
-                    required_limits: wgpu::Limits::default(),
+                    required_features: config.required_features | optional_features,
+                    required_limits: wgpu::Limits::downlevel_defaults()
+                        .using_resolution(adapter.limits()),
                 },
                 None,
             )
             .await
-            .unwrap();
+            .map_err(EngineError::RequestDevice)
+    }
+
+    /// The highest sample count up to the requested one that the format can be rendered with,
+    /// and the depth target too when there is one.
+    fn supported_msaa_samples(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        config: &EngineConfig,
+    ) -> u32 {
+        // Counts other than 1 and 4 are only usable with adapter specific format features
+        let adapter_specific = device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+        let flags = adapter.get_texture_format_features(format).flags;
+        let depth_flags = adapter.get_texture_format_features(DEPTH_FORMAT).flags;
+        [16, 8, 4, 2]
+            .into_iter()
+            .filter(|count| *count <= config.msaa_samples)
+            .filter(|count| !config.depth_buffer || depth_flags.sample_count_supported(*count))
+            .find(|count| flags.sample_count_supported(*count) && (*count == 4 || adapter_specific))
+            .unwrap_or(1)
+    }
+
+    fn build(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_configuration: wgpu::SurfaceConfiguration,
+        surface: Option<wgpu::Surface<'static>>,
+        window: Option<Arc<winit::window::Window>>,
+        msaa_samples: u32,
+        config: &EngineConfig,
+    ) -> Self {
         let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
@@ -64,27 +329,6 @@ impl Engine {
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        let surface_capabilities = surface.get_capabilities(&adapter);
-        let present_mode;
-        if v_sync {
-            present_mode = wgpu::PresentMode::AutoVsync;
-        } else {
-            present_mode = wgpu::PresentMode::AutoNoVsync;
-        }
-        // Check this...may need to specifically set it to some sRGB value
-        let surface_format = surface_capabilities.formats[0];
-        let surface_configuration = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: window.inner_size().width,
-            height: window.inner_size().height,
-            present_mode,
-            alpha_mode: surface_capabilities.alpha_modes[0],
-            view_formats: Vec::new(),
-            desired_maximum_frame_latency: Default::default(),
-        };
-        surface.configure(&device, &surface_configuration);
-
         let shader_module =
             device.create_shader_module(wgpu::include_wgsl!("../shaders/shader.wgsl"));
 
@@ -131,11 +375,221 @@ impl Engine {
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let depth_state = |depth_write_enabled, depth_compare| {
+            config.depth_buffer.then(|| wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            })
+        };
+        // Opaque texels of sprites write depth first, then every sprite is drawn testing against
+        // it without writing, so translucent texels don't hide sprites drawn after them.
+        // The background never hides anything so it skips the depth test.
+        // Clearing a viewport resets its depth, so cameras aren't tested against each other.
+        let sprite_depth = depth_state(true, wgpu::CompareFunction::LessEqual);
+        let blended_depth = depth_state(false, wgpu::CompareFunction::LessEqual);
+        let background_depth = depth_state(false, wgpu::CompareFunction::Always);
+        let clear_depth = depth_state(true, wgpu::CompareFunction::Always);
+        let render_pipelines = BlendMode::ALL
+            .into_iter()
+            .map(|blend_mode| {
+                let pipeline = Engine::create_render_pipeline(
+                    &device,
+                    &render_pipeline_layout,
+                    &shader_module,
+                    surface_configuration.format,
+                    msaa_samples,
+                    blended_depth.clone(),
+                    blend_mode,
+                );
+                (blend_mode, pipeline)
+            })
+            .collect();
+        let opaque_pipelines = BlendMode::ALL
+            .into_iter()
+            .filter(|blend_mode| config.depth_buffer && blend_mode.writes_depth())
+            .map(|blend_mode| {
+                let pipeline = Engine::create_render_pipeline(
+                    &device,
+                    &render_pipeline_layout,
+                    &shader_module,
+                    surface_configuration.format,
+                    msaa_samples,
+                    sprite_depth.clone(),
+                    blend_mode,
+                );
+                (blend_mode, pipeline)
+            })
+            .collect();
+        let background_pipeline = Engine::create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            &shader_module,
+            surface_configuration.format,
+            msaa_samples,
+            background_depth,
+            BlendMode::Premultiplied,
+        );
+        let clear_pipeline = Engine::create_clear_pipeline(
+            &device,
+            surface_configuration.format,
+            msaa_samples,
+            clear_depth,
+        );
+
+        let background = None;
+        let upscaler = Upscaler::new(&device, surface_configuration.format);
+        let size = PhysicalSize::new(surface_configuration.width, surface_configuration.height);
+        let msaa_target =
+            Engine::create_msaa_target(&device, surface_configuration.format, size, msaa_samples);
+        let depth_target = match config.depth_buffer {
+            true => Some(Engine::create_depth_target(&device, size, msaa_samples)),
+            false => None,
+        };
+        let offscreen = match surface {
+            Some(_) => None,
+            None => Some(Engine::create_offscreen_texture(
+                &device,
+                &surface_configuration,
+            )),
+        };
+        Self {
+            surface,
+            device,
+            queue,
+            surface_configuration,
+            window,
+            offscreen,
+            capture: FrameCapture::new(),
+            render_pipelines,
+            opaque_pipelines,
+            background_pipeline,
+            clear_pipeline,
+            texture_bgl,
+            background,
+            index_buffer,
+            upscaler,
+            msaa_samples,
+            msaa_target,
+            depth_target,
+            clear_colour: config.clear_colour,
+            present_modes: Vec::new(),
+            window_mode: WindowMode::Windowed,
+            surface_outdated: false,
+        }
+    }
+
+    fn create_offscreen_texture(
+        device: &wgpu::Device,
+        configuration: &wgpu::SurfaceConfiguration,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen target"),
+            size: wgpu::Extent3d {
+                width: configuration.width,
+                height: configuration.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: configuration.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        })
+    }
+
+    fn create_msaa_target(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+        msaa_samples: u32,
+    ) -> Option<wgpu::TextureView> {
+        if msaa_samples <= 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: msaa_samples,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    fn create_depth_target(
+        device: &wgpu::Device,
+        size: PhysicalSize<u32>,
+        msaa_samples: u32,
+    ) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: msaa_samples,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    // The MSAA and depth targets have to match whatever the layers are drawn into
+    fn update_render_targets(&mut self) {
+        self.msaa_target = Engine::create_msaa_target(
+            &self.device,
+            self.surface_configuration.format,
+            self.render_size(),
+            self.msaa_samples,
+        );
+        if self.depth_target.is_some() {
+            self.depth_target = Some(Engine::create_depth_target(
+                &self.device,
+                self.render_size(),
+                self.msaa_samples,
+            ));
+        }
+    }
+
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        msaa_samples: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+        blend_mode: BlendMode,
+    ) -> wgpu::RenderPipeline {
+        // Writing depth needs all but opaque texels discarded, Alpha blending needs straight colour
+        let writes_depth = depth_stencil
+            .as_ref()
+            .is_some_and(|depth_stencil| depth_stencil.depth_write_enabled);
+        let fragment_entry = match (blend_mode, writes_depth) {
+            (BlendMode::Alpha, false) => "frg_straight",
+            (BlendMode::Alpha, true) => "frg_straight_depth",
+            (_, false) => "frg_main",
+            (_, true) => "frg_depth",
+        };
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
+            layout: Some(layout),
             vertex: wgpu::VertexState {
-                module: &shader_module,
+                module: shader_module,
                 entry_point: "vrt_main",
                 buffers: &[Vertex::layout(), Entity2DRaw::layout()],
             },
@@ -148,9 +602,60 @@ impl Engine {
                 polygon_mode: wgpu::PolygonMode::Fill,
                 conservative: false,
             },
-            depth_stencil: None,
+            depth_stencil,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: msaa_samples,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: fragment_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend_mode.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        })
+    }
+
+    // Outputs white, blended with the blend constant so the colour can change without a buffer.
+    // Drawn at the far plane, so with a depth buffer it also resets the viewport's depth.
+    fn create_clear_pipeline(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        msaa_samples: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let shader_module =
+            device.create_shader_module(wgpu::include_wgsl!("../shaders/clear.wgsl"));
+        let blend_component = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Clear pipeline"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vrt_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil,
+            multisample: wgpu::MultisampleState {
+                count: msaa_samples,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -158,35 +663,50 @@ impl Engine {
                 module: &shader_module,
                 entry_point: "frg_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_configuration.format,
-                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: blend_component,
+                        alpha: blend_component,
+                    }),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
             multiview: None,
-        });
-
-        let background = None;
-        Self {
-            surface,
-            device,
-            queue,
-            surface_configuration,
-            window,
-            render_pipeline,
-            texture_bgl,
-            background,
-            index_buffer,
-        }
+        })
     }
 
+    /// Reconfigures the render target, `update_camera` and `render` do this themselves when
+    /// the window changes size.
     pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
         if size.width > 0 && size.height > 0 {
             self.surface_configuration.width = size.width;
             self.surface_configuration.height = size.height;
-            self.surface
-                .configure(&self.device, &self.surface_configuration);
+            match self.surface.as_ref() {
+                Some(surface) => surface.configure(&self.device, &self.surface_configuration),
+                None => {
+                    self.offscreen = Some(Engine::create_offscreen_texture(
+                        &self.device,
+                        &self.surface_configuration,
+                    ))
+                }
+            }
+            self.update_render_targets();
+        }
+    }
+
+    // Resizes to match the window if it changed, false when minimised with nothing to draw to
+    fn follow_window_size(&mut self) -> bool {
+        let size = match self.window.as_ref() {
+            Some(window) => window.inner_size(),
+            None => return true,
+        };
+        if size.width == 0 || size.height == 0 {
+            return false;
+        }
+        if size != self.size() {
+            self.resize(size);
         }
+        true
     }
 
     pub fn input(&mut self, _event: &winit::event::Event<()>, _delta: &std::time::Duration) {
@@ -202,64 +722,269 @@ impl Engine {
         // if accuracy is a problem, change to floats
     }
 
+    /// Renders to the window surface, or to the offscreen target when headless.
+    /// Lost, outdated and timed out surfaces skip the frame rather than failing,
+    /// only running out of memory is an error.
     pub fn render(
         &mut self,
         entities: &Vec<Layer2D>,
         camera: &Camera2D,
-    ) -> Result<(), wgpu::SurfaceError> {
-        let surface_texture = self.surface.get_current_texture()?;
-        let texture_view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    ) -> Result<FrameStatus, wgpu::SurfaceError> {
+        self.render_views(entities, &[camera])
+    }
+
+    /// Renders the layers once per camera, each into its own viewport of the same frame.
+    /// Cameras later in the slice are drawn on top where viewports overlap.
+    pub fn render_views(
+        &mut self,
+        entities: &Vec<Layer2D>,
+        cameras: &[&Camera2D],
+    ) -> Result<FrameStatus, wgpu::SurfaceError> {
+        // Between frames, nothing is using the surface's textures
+        if self.surface_outdated {
+            if let Some(surface) = self.surface.as_ref() {
+                surface.configure(&self.device, &self.surface_configuration);
+            }
+            self.surface_outdated = false;
+        }
+        if !self.follow_window_size() {
+            return Ok(FrameStatus::Skipped(SkipReason::Minimised));
+        }
+        let surface_texture = match self.surface.as_ref() {
+            Some(surface) => match surface.get_current_texture() {
+                Ok(surface_texture) => Some(surface_texture),
+                Err(wgpu::SurfaceError::OutOfMemory) => {
+                    return Err(wgpu::SurfaceError::OutOfMemory)
+                }
+                Err(wgpu::SurfaceError::Timeout) => {
+                    return Ok(FrameStatus::Skipped(SkipReason::Timeout))
+                }
+                Err(wgpu::SurfaceError::Outdated) => {
+                    surface.configure(&self.device, &self.surface_configuration);
+                    return Ok(FrameStatus::Skipped(SkipReason::SurfaceOutdated));
+                }
+                Err(wgpu::SurfaceError::Lost) => {
+                    surface.configure(&self.device, &self.surface_configuration);
+                    return Ok(FrameStatus::Skipped(SkipReason::SurfaceLost));
+                }
+            },
+            None => None,
+        };
+        // Only once there is something to draw to, so skipped frames don't use up a capture
+        let capture_frame = FrameCaptureSystem::begin_frame(&mut self.capture);
+        match surface_texture {
+            Some(surface_texture) => {
+                let texture_view = surface_texture
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                self.draw(&texture_view, entities, cameras);
+                if capture_frame {
+                    let frame = if self
+                        .surface_configuration
+                        .usage
+                        .contains(wgpu::TextureUsages::COPY_SRC)
+                    {
+                        texture_to_image(&self.device, &self.queue, &surface_texture.texture)
+                    } else {
+                        self.render_views_to_image(entities, cameras)
+                    };
+                    FrameCaptureSystem::end_frame(&mut self.capture, frame);
+                }
+                // Still presentable, but reconfiguring should get a better match
+                if surface_texture.suboptimal {
+                    self.surface_outdated = true;
+                }
+                surface_texture.present();
+            }
+            None => {
+                let target = self.offscreen.as_ref().unwrap();
+                let texture_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+                self.draw(&texture_view, entities, cameras);
+                if capture_frame {
+                    let frame = texture_to_image(&self.device, &self.queue, target);
+                    FrameCaptureSystem::end_frame(&mut self.capture, frame);
+                }
+            }
+        }
+        Ok(FrameStatus::Presented)
+    }
+
+    /// Renders the layers into an offscreen texture and reads the frame back.
+    /// Works with or without a window, the image has the dimensions of the render target.
+    pub fn render_to_image(
+        &mut self,
+        entities: &Vec<Layer2D>,
+        camera: &Camera2D,
+    ) -> Result<RgbaImage> {
+        self.render_views_to_image(entities, &[camera])
+    }
+
+    pub fn render_views_to_image(
+        &mut self,
+        entities: &Vec<Layer2D>,
+        cameras: &[&Camera2D],
+    ) -> Result<RgbaImage> {
+        if self.offscreen.as_ref().map(|target| target.size())
+            != Some(wgpu::Extent3d {
+                width: self.surface_configuration.width,
+                height: self.surface_configuration.height,
+                depth_or_array_layers: 1,
+            })
+        {
+            self.offscreen = Some(Engine::create_offscreen_texture(
+                &self.device,
+                &self.surface_configuration,
+            ));
+        }
+        let target = self.offscreen.as_ref().unwrap();
+        let texture_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        self.draw(&texture_view, entities, cameras);
+        texture_to_image(&self.device, &self.queue, target)
+    }
+
+    fn draw(
+        &self,
+        texture_view: &wgpu::TextureView,
+        entities: &Vec<Layer2D>,
+        cameras: &[&Camera2D],
+    ) {
         let mut command_encoder =
             self.device
                 .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                     label: Some("Command encoder"),
                 });
+        // With a virtual resolution the layers are drawn small, then scaled up to the target
+        let scene_view = self.upscaler.target_view().unwrap_or(texture_view);
         let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &texture_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.0,
-                        g: 0.5,
-                        b: 0.5,
-                        a: 0.0,
-                    }),
-                    store: wgpu::StoreOp::Store,
+            color_attachments: &[Some(match self.msaa_target.as_ref() {
+                Some(msaa_target) => wgpu::RenderPassColorAttachment {
+                    view: msaa_target,
+                    resolve_target: Some(scene_view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_colour),
+                        store: wgpu::StoreOp::Discard,
+                    },
+                },
+                None => wgpu::RenderPassColorAttachment {
+                    view: scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_colour),
+                        store: wgpu::StoreOp::Store,
+                    },
                 },
             })],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: self.depth_target.as_ref().map(|depth_target| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_target,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
             timestamp_writes: None,
             occlusion_query_set: None,
         });
-        render_pass.set_pipeline(&self.render_pipeline);
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.set_blend_constant(self.clear_colour);
 
-        match self.background.as_ref() {
-            Some(bg) => {
-                render_pass.set_bind_group(0, bg.bind_group(), &[]);
-                render_pass.set_bind_group(1, bg.camera_bind_group(), &[]);
-                render_pass.set_vertex_buffer(0, bg.vertex_buffer());
-                render_pass.set_vertex_buffer(1, bg.entity_buffer());
-                render_pass.draw_indexed(0..6, 0, 0..1);
+        for camera in cameras {
+            // The current size, in case the camera hasn't been updated since a resize
+            let viewport = camera.viewport_pixels_in(self.render_size());
+            // Clamp to the target, wgpu rejects viewports and scissors that fall outside it,
+            // which rounding can cause even for viewports that should fit exactly
+            let target_width = self.render_size().width;
+            let target_height = self.render_size().height;
+            let left = viewport.x.clamp(0.0, target_width as f32);
+            let top = viewport.y.clamp(0.0, target_height as f32);
+            let right = (viewport.x + viewport.width).clamp(left, target_width as f32);
+            let bottom = (viewport.y + viewport.height).clamp(top, target_height as f32);
+            let x = (left as u32).min(target_width);
+            let y = (top as u32).min(target_height);
+            let width = (right as u32).min(target_width) - x;
+            let height = (bottom as u32).min(target_height) - y;
+            if width == 0 || height == 0 {
+                continue;
             }
-            None => (),
-        };
+            render_pass.set_viewport(left, top, right - left, bottom - top, 0.0, 1.0);
+            render_pass.set_scissor_rect(x, y, width, height);
+
+            // Overlapping cameras shouldn't show through or depth test against each other
+            render_pass.set_pipeline(&self.clear_pipeline);
+            render_pass.draw(0..3, 0..1);
 
-        render_pass.set_bind_group(1, camera.bind_group(), &[]);
-        for layer in entities {
-            render_pass.set_bind_group(0, layer.bind_group(), &[]);
-            render_pass.set_vertex_buffer(0, layer.vertex_buffer());
-            render_pass.set_vertex_buffer(1, layer.entity_buffer().unwrap());
-            render_pass.draw_indexed(0..6 as u32, 0, 0..layer.entity_count() as u32);
+            match self.background.as_ref() {
+                Some(bg) => {
+                    render_pass.set_pipeline(&self.background_pipeline);
+                    render_pass.set_bind_group(0, bg.bind_group(), &[]);
+                    render_pass.set_bind_group(1, bg.camera_bind_group(), &[]);
+                    render_pass.set_vertex_buffer(0, bg.vertex_buffer());
+                    render_pass.set_vertex_buffer(1, bg.entity_buffer());
+                    render_pass.draw_indexed(0..6, 0, 0..1);
+                }
+                None => (),
+            };
+
+            render_pass.set_bind_group(1, camera.bind_group(), &[]);
+            for pipelines in [&self.opaque_pipelines, &self.render_pipelines] {
+                for layer in entities {
+                    if !camera.is_layer_visible(layer.id()) {
+                        continue;
+                    }
+                    let pipeline = match pipelines.get(&layer.blend_mode()) {
+                        Some(pipeline) => pipeline,
+                        None => continue,
+                    };
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_bind_group(0, layer.bind_group(), &[]);
+                    render_pass.set_vertex_buffer(0, layer.vertex_buffer());
+                    render_pass.set_vertex_buffer(1, layer.entity_buffer().unwrap());
+                    render_pass.draw_indexed(0..6 as u32, 0, 0..layer.entity_count() as u32);
+                }
+            }
         }
+        // Some backends clip the MSAA resolve to the last scissor rect
+        let size = self.render_size();
+        render_pass.set_scissor_rect(0, 0, size.width, size.height);
         drop(render_pass);
+        UpscalerSystem::upscale(
+            &self.upscaler,
+            &mut command_encoder,
+            texture_view,
+            self.size(),
+        );
         self.queue.submit(std::iter::once(command_encoder.finish()));
-        surface_texture.present();
-        Ok(())
+    }
+
+    pub fn capture_next_frame(&mut self) {
+        FrameCaptureSystem::capture_next_frame(&mut self.capture);
+    }
+
+    pub fn take_captured_frame(&mut self) -> Option<RgbaImage> {
+        FrameCaptureSystem::take_frame(&mut self.capture)
+    }
+
+    pub fn save_screenshot(&mut self, path: impl Into<PathBuf>) {
+        FrameCaptureSystem::save_next_frame(&mut self.capture, path);
+    }
+
+    pub fn start_frame_capture(
+        &mut self,
+        interval: u32,
+        directory: impl Into<PathBuf>,
+    ) -> Result<()> {
+        FrameCaptureSystem::start_sequence(&mut self.capture, interval, directory)
+    }
+
+    pub fn stop_frame_capture(&mut self) {
+        FrameCaptureSystem::stop_sequence(&mut self.capture);
+    }
+
+    pub fn take_capture_error(&mut self) -> Option<anyhow::Error> {
+        FrameCaptureSystem::take_error(&mut self.capture)
     }
 
     pub fn device(&self) -> &wgpu::Device {
@@ -270,12 +995,106 @@ impl Engine {
         &self.queue
     }
 
-    pub fn surface(&self) -> &wgpu::Surface {
-        &self.surface
+    pub fn surface(&self) -> Option<&wgpu::Surface> {
+        self.surface.as_ref()
+    }
+
+    pub fn window(&self) -> Option<&winit::window::Window> {
+        self.window.as_deref()
+    }
+
+    pub fn set_clear_colour(&mut self, clear_colour: wgpu::Color) {
+        self.clear_colour = clear_colour;
+    }
+
+    pub fn clear_colour(&self) -> wgpu::Color {
+        self.clear_colour
+    }
+
+    /// Switches between windowed, borderless and fullscreen on the window's current monitor.
+    pub fn set_window_mode(&mut self, window_mode: WindowMode) {
+        if let Some(window) = self.window.as_ref() {
+            window.set_fullscreen(window_mode.to_fullscreen(window.current_monitor()));
+            self.window_mode = window_mode;
+        }
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        self.window_mode
     }
 
-    pub fn window(&self) -> &winit::window::Window {
-        &self.window
+    /// Changes the present mode, taking effect from the next frame. Auto modes are
+    /// always accepted, others must be in `supported_present_modes`.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) -> Result<()> {
+        let auto = matches!(
+            present_mode,
+            wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+        );
+        if self.surface.is_some() && !auto && !self.present_modes.contains(&present_mode) {
+            bail!(EffectError::new(
+                "Present mode is not supported by the surface"
+            ));
+        }
+        self.surface_configuration.present_mode = present_mode;
+        self.surface_outdated = true;
+        Ok(())
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.surface_configuration.present_mode
+    }
+
+    pub fn supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        &self.present_modes
+    }
+
+    /// How many frames may be queued ahead of the one on screen, lower reduces
+    /// input lag at the cost of throughput. Takes effect from the next frame.
+    pub fn set_frame_latency(&mut self, frame_latency: u32) -> Result<()> {
+        if frame_latency == 0 {
+            bail!(EffectError::new("Frame latency must be at least 1"));
+        }
+        self.surface_configuration.desired_maximum_frame_latency = frame_latency;
+        self.surface_outdated = true;
+        Ok(())
+    }
+
+    pub fn frame_latency(&self) -> u32 {
+        self.surface_configuration.desired_maximum_frame_latency
+    }
+
+    /// Renders every layer at a fixed resolution, then scales it up to the window by
+    /// the largest whole number that fits, with bars around the rest. None to turn it off.
+    pub fn set_virtual_resolution(&mut self, size: Option<PhysicalSize<u32>>) -> Result<()> {
+        UpscalerSystem::set_virtual_resolution(&mut self.upscaler, &self.device, size)?;
+        self.update_render_targets();
+        Ok(())
+    }
+
+    pub fn virtual_resolution(&self) -> Option<PhysicalSize<u32>> {
+        self.upscaler.virtual_size()
+    }
+
+    /// The resolution layers are drawn at and cameras fit to, the virtual resolution if set.
+    pub fn render_size(&self) -> PhysicalSize<u32> {
+        self.upscaler.virtual_size().unwrap_or(self.size())
+    }
+
+    /// Converts a window position, eg the mouse, to the render resolution for picking
+    /// and `Camera2DSystem::screen_to_world`. None when over the bars.
+    pub fn window_to_render_position(
+        &self,
+        position: winit::dpi::PhysicalPosition<f64>,
+    ) -> Option<winit::dpi::PhysicalPosition<f64>> {
+        UpscalerSystem::window_to_virtual(&self.upscaler, self.size(), position)
+    }
+
+    /// The dimensions of the render target, the window surface or offscreen texture.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(
+            self.surface_configuration.width,
+            self.surface_configuration.height,
+        )
     }
 
     pub fn init_layer(
@@ -287,7 +1106,7 @@ impl Engine {
     ) -> Result<Layer2D> {
         Layer2D::new(
             id,
-            self.window.inner_size(),
+            self.size(),
             textures,
             &self.device,
             &self.queue,
@@ -301,18 +1120,22 @@ impl Engine {
         Layer2DSystem::set_entities(layer, entities, &self.device, &self.queue)
     }
 
-    pub fn update_camera(&self, camera: &mut Camera2D) {
+    /// Uploads the camera, first fitting it to the current size of the render target.
+    /// Window resizes are applied here, so cameras see the new size on the frame it changes.
+    pub fn update_camera(&mut self, camera: &mut Camera2D) {
+        self.follow_window_size();
+        if camera.target_size() != self.render_size() {
+            Camera2DSystem::set_target_size(camera, self.render_size());
+        }
         Camera2DSystem::update(camera, &self.queue);
     }
 
     pub fn init_camera(&self, fov: f32) -> Camera2D {
-        let dims = self.window.inner_size();
-        Camera2D::new(
-            &self.device,
-            fov,
-            (dims.width as f32) / (dims.height as f32),
-            0.5,
-        )
+        Camera2D::new(&self.device, fov, self.render_size(), 0.5)
+    }
+
+    pub fn init_camera_with_projection(&self, projection: CameraProjection) -> Camera2D {
+        Camera2D::with_projection(&self.device, projection, self.render_size(), 0.5)
     }
 
     pub fn set_background(&mut self, texture: Texture2D, pixel_art: bool) -> Result<()> {
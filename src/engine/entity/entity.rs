@@ -3,7 +3,10 @@ use winit::dpi::PhysicalSize;
 
 use crate::engine::{
     layer::layer::{Layer2D, LayerID},
-    primitives::vector::Vector3,
+    primitives::{
+        rect::Rect,
+        vector::{Vector2, Vector3},
+    },
     texture::texture2d::TextureID,
     transform::transform::{Transform2D, Transform2DSystem},
     util::effect_error::EffectError,
@@ -15,11 +18,14 @@ pub struct Entity2DRaw {
     transform: [[f32; 4]; 4],
     texture_index: [f32; 2],
     texture_size: [f32; 2],
+    tint: [f32; 4],
+    uv_rect: [f32; 4],
+    flip: [f32; 2],
 }
 
 impl Entity2DRaw {
-    const ATTRIBUTE_ARRAY: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![2 => Float32x4, 3=> Float32x4,
-        4=> Float32x4,5=> Float32x4,6=> Float32x2, 7=>Float32x2];
+    const ATTRIBUTE_ARRAY: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![2 => Float32x4, 3=> Float32x4,
+        4=> Float32x4,5=> Float32x4,6=> Float32x2, 7=>Float32x2, 8=>Float32x4, 9=>Float32x4, 10=>Float32x2];
 
     pub fn layout() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
@@ -28,6 +34,31 @@ impl Entity2DRaw {
             attributes: &Self::ATTRIBUTE_ARRAY,
         }
     }
+
+    pub fn transform(&self) -> glam::Mat4 {
+        glam::Mat4::from_cols_array_2d(&self.transform)
+    }
+
+    pub fn texture_index(&self) -> [f32; 2] {
+        self.texture_index
+    }
+
+    pub fn texture_size(&self) -> [f32; 2] {
+        self.texture_size
+    }
+
+    /// Tint with the opacity already applied to alpha
+    pub fn tint(&self) -> [f32; 4] {
+        self.tint
+    }
+
+    pub fn uv_rect(&self) -> [f32; 4] {
+        self.uv_rect
+    }
+
+    pub fn flip(&self) -> [bool; 2] {
+        [self.flip[0] != 0.0, self.flip[1] != 0.0]
+    }
 }
 
 pub struct Entity2D {
@@ -36,6 +67,11 @@ pub struct Entity2D {
     texture: TextureID,
     texture_index: [u32; 2],
     texture_size: PhysicalSize<f32>,
+    tint: [f32; 4],
+    opacity: f32,
+    uv_rect: Rect<f32>,
+    flip: [bool; 2],
+    parent_transform: glam::Mat4,
 }
 
 impl Entity2D {
@@ -51,14 +87,32 @@ impl Entity2D {
             texture,
             texture_index,
             texture_size,
+            tint: [1.0, 1.0, 1.0, 1.0],
+            opacity: 1.0,
+            uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+            flip: [false, false],
+            parent_transform: glam::Mat4::IDENTITY,
         }
     }
 
     pub fn to_raw(&self) -> Entity2DRaw {
         Entity2DRaw {
-            transform: self.transform.to_raw().inner,
+            transform: self.world_matrix().to_cols_array_2d(),
             texture_index: [self.texture_index[0] as f32, self.texture_index[1] as f32],
             texture_size: self.texture_size.into(),
+            tint: [
+                self.tint[0],
+                self.tint[1],
+                self.tint[2],
+                self.tint[3] * self.opacity,
+            ],
+            uv_rect: [
+                self.uv_rect.x,
+                self.uv_rect.y,
+                self.uv_rect.width,
+                self.uv_rect.height,
+            ],
+            flip: [self.flip[0] as u32 as f32, self.flip[1] as u32 as f32],
         }
     }
 
@@ -66,8 +120,38 @@ impl Entity2D {
         &self.layer
     }
 
-    pub fn position(&self) -> &Vector3<f32> {
-        &self.transform.position()
+    pub fn position(&self) -> Vector3<f32> {
+        self.transform.position()
+    }
+
+    pub fn transform(&self) -> &Transform2D {
+        &self.transform
+    }
+
+    /// The local transform combined with any parent transform.
+    pub fn world_matrix(&self) -> glam::Mat4 {
+        self.parent_transform * self.transform.matrix()
+    }
+
+    pub fn world_position(&self) -> Vector3<f32> {
+        let position = self.world_matrix().w_axis;
+        Vector3::new(position.x, position.y, position.z)
+    }
+
+    pub fn tint(&self) -> [f32; 4] {
+        self.tint
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn uv_rect(&self) -> Rect<f32> {
+        self.uv_rect
+    }
+
+    pub fn flip(&self) -> [bool; 2] {
+        self.flip
     }
 }
 
@@ -90,10 +174,58 @@ impl EntitySystem2D {
     pub fn set_position(entity: &mut Entity2D, position: Vector3<f32>) {
         Transform2DSystem::translate(&mut entity.transform, position);
     }
+    pub fn translate_by(entity: &mut Entity2D, offset: Vector3<f32>) {
+        Transform2DSystem::translate_by(&mut entity.transform, offset);
+    }
     pub fn set_rotation(entity: &mut Entity2D, degrees: f32) {
         Transform2DSystem::rotate(&mut entity.transform, degrees);
     }
+    pub fn rotate_by(entity: &mut Entity2D, degrees: f32) {
+        Transform2DSystem::rotate_by(&mut entity.transform, degrees);
+    }
+    /// Turns the entity so its forward (local up) axis points at the target.
+    pub fn look_at(entity: &mut Entity2D, target: Vector2<f32>) {
+        Transform2DSystem::look_at(&mut entity.transform, target);
+    }
     pub fn set_scale(entity: &mut Entity2D, scale: f32) {
         Transform2DSystem::scale(&mut entity.transform, scale);
     }
+    pub fn set_scale_xy(entity: &mut Entity2D, scale: Vector2<f32>) {
+        Transform2DSystem::scale_xy(&mut entity.transform, scale);
+    }
+    /// See `Transform2DSystem::set_pivot`.
+    pub fn set_pivot(entity: &mut Entity2D, pivot: Vector2<f32>) {
+        Transform2DSystem::set_pivot(&mut entity.transform, pivot);
+    }
+
+    /// Places the entity's transform inside another. This is managed by `Scene2D`
+    /// for entities in a scene, but can be used for custom hierarchies.
+    pub fn set_parent_transform(entity: &mut Entity2D, parent_transform: glam::Mat4) {
+        entity.parent_transform = parent_transform;
+    }
+
+    /// RGBA colour multiplied with the texture, white leaves the texture unchanged.
+    /// The alpha component is combined with the opacity.
+    pub fn set_tint(entity: &mut Entity2D, tint: [f32; 4]) {
+        entity.tint = tint;
+    }
+
+    /// Fades the entity, 0.0 is invisible and 1.0 is fully opaque.
+    pub fn set_opacity(entity: &mut Entity2D, opacity: f32) {
+        entity.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Mirrors the texture horizontally and / or vertically, within its source rectangle.
+    pub fn set_flip(entity: &mut Entity2D, horizontal: bool, vertical: bool) {
+        entity.flip = [horizontal, vertical];
+    }
+
+    /// Shows only part of the texture. The rectangle is relative to the texture,
+    /// (0, 0) is the top left corner and (1, 1) the bottom right.
+    /// The quad is cropped to match, so the visible part stays where it would be
+    /// in the whole texture, eg a health bar cropped to its left half.
+    /// Passing None shows the whole texture again.
+    pub fn set_uv_rect(entity: &mut Entity2D, uv_rect: Option<Rect<f32>>) {
+        entity.uv_rect = uv_rect.unwrap_or(Rect::new(0.0, 0.0, 1.0, 1.0));
+    }
 }
@@ -14,6 +14,104 @@ use crate::engine::{
 #[derive(std::cmp::PartialEq, std::cmp::Eq, Hash, Clone, Copy, Debug, PartialOrd, Ord)]
 pub struct LayerID(pub u32);
 
+/// Order the entities of a layer are drawn in, later entities are drawn on top.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum SortMode {
+    /// The order given to `set_entities`
+    #[default]
+    None,
+    /// Highest y first, so entities lower on screen are in front, as in top down games.
+    /// Uses the world position, so set the pivot to the feet of characters and trees.
+    Y,
+    /// Lowest z first, needed for partly transparent sprites with the depth buffer.
+    /// Blending is only correct within a layer sorted this way, and across layers
+    /// only when the layer order matches z.
+    Z,
+    /// Lowest key first
+    Custom(fn(&Entity2D) -> f32),
+}
+
+/// How a layer's entities are combined with what is already drawn.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum BlendMode {
+    /// Standard transparency for textures with straight alpha
+    Alpha,
+    /// Standard transparency for textures with premultiplied alpha
+    #[default]
+    Premultiplied,
+    /// Brightens what is behind, for glows, fire and lights
+    Additive,
+    /// Darkens what is behind, for shadows and tinting overlays
+    Multiply,
+    /// Brightens what is behind without washing out as quickly as additive
+    Screen,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 5] = [
+        BlendMode::Alpha,
+        BlendMode::Premultiplied,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+    ];
+
+    /// Whether opaque texels write depth when the depth buffer is on. Additive, multiply and
+    /// screen are see through, so they are only hidden by what is in front of them.
+    pub fn writes_depth(self) -> bool {
+        matches!(self, BlendMode::Alpha | BlendMode::Premultiplied)
+    }
+
+    // Every mode except Alpha expects the shader to output premultiplied colour
+    pub fn blend_state(self) -> wgpu::BlendState {
+        let keep_alpha = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Zero,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        match self {
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: keep_alpha,
+            },
+            BlendMode::Multiply => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: keep_alpha,
+            },
+            BlendMode::Screen => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        }
+    }
+}
+
+impl SortMode {
+    // Equal keys for None, so a stable sort leaves the order unchanged
+    fn key(self, entity: &Entity2D) -> f32 {
+        match self {
+            SortMode::None => 0.0,
+            SortMode::Y => -entity.world_position().y,
+            SortMode::Z => entity.world_position().z,
+            SortMode::Custom(key) => key(entity),
+        }
+    }
+}
+
 // Takes final ownership of textures, the data etc.
 // When a entity wants to get the texture offset, it must get the data from here.
 pub struct Layer2D {
@@ -24,6 +122,12 @@ pub struct Layer2D {
     entity_count: usize,
     entity_maximum: usize,
     entity_buffer: Option<wgpu::Buffer>,
+    // What was last uploaded, kept for queries such as picking
+    instances: Vec<Entity2DRaw>,
+    // Position in the slice given to set_entities of each instance, as sorting reorders them
+    source_indices: Vec<usize>,
+    sort_mode: SortMode,
+    blend_mode: BlendMode,
     dimensions: winit::dpi::PhysicalSize<u32>,
 }
 
@@ -66,6 +170,10 @@ impl Layer2D {
             entity_count,
             entity_buffer: None,
             entity_maximum,
+            instances: Vec::new(),
+            source_indices: Vec::new(),
+            sort_mode: SortMode::None,
+            blend_mode: BlendMode::default(),
             dimensions,
         })
     }
@@ -131,6 +239,30 @@ impl Layer2D {
     pub fn atlas_dimensions(&self) -> PhysicalSize<u32> {
         self.atlas.dimensions()
     }
+
+    /// The entity data last set on the layer, in draw order after sorting by the sort mode.
+    /// Use `source_index` to map an instance back to the slice given to `set_entities`.
+    pub fn instances(&self) -> &[Entity2DRaw] {
+        &self.instances
+    }
+
+    /// Where the uploaded instance came from in the slice given to `set_entities`.
+    pub fn source_index(&self, instance: usize) -> usize {
+        self.source_indices[instance]
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort_mode
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Alpha of the atlas texel at the given texture coordinates.
+    pub fn texel_alpha(&self, tex_coords: [f32; 2]) -> u8 {
+        self.atlas.alpha(tex_coords)
+    }
 }
 
 pub struct Layer2DSystem;
@@ -162,12 +294,11 @@ impl Layer2DSystem {
 
     // alloc buffer to 2x the size and set new max entity count
     fn create_entity_buffer(
-        entities: &[&Entity2D],
+        ents: &[Entity2DRaw],
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> wgpu::Buffer {
-        let ents = entities.iter().map(|e| e.to_raw()).collect::<Vec<_>>();
-        let data: &[u8] = bytemuck::cast_slice(ents.as_slice());
+        let data: &[u8] = bytemuck::cast_slice(ents);
         let size = std::mem::size_of_val(data) as u64;
         Layer2DSystem::alloc_buffer(data, size * 2, device, queue, "Entity Buffer", false)
     }
@@ -212,6 +343,16 @@ impl Layer2DSystem {
         Layer2DSystem::alloc_buffer(data, size, device, queue, "Vertex Buffer", false)
     }
 
+    /// How the layer's entities are ordered before being uploaded,
+    /// applies from the next `set_entities`.
+    pub fn set_sort_mode(layer: &mut Layer2D, sort_mode: SortMode) {
+        layer.sort_mode = sort_mode;
+    }
+
+    pub fn set_blend_mode(layer: &mut Layer2D, blend_mode: BlendMode) {
+        layer.blend_mode = blend_mode;
+    }
+
     /// Set the entity data for the particular layer.
     /// Ensure every entity has a texture from the specified layer otherwise you will run into problems.
     pub fn set_entities(
@@ -223,17 +364,35 @@ impl Layer2DSystem {
         // allocating exactly amount needed each time may increase the number of allocations needed..
         // perhaps a strategy of allocatin 2X needed data would be better
         layer.entity_count = entities.len();
+        layer.source_indices.clear();
+        layer.source_indices.extend(0..entities.len());
+        if !matches!(layer.sort_mode, SortMode::None) {
+            // Keys first, rather than recomputing world positions for every comparison.
+            // Stable, so entities with the same key keep the order they were given in.
+            let keys = entities
+                .iter()
+                .map(|entity| layer.sort_mode.key(entity))
+                .collect::<Vec<_>>();
+            layer
+                .source_indices
+                .sort_by(|a, b| keys[*a].total_cmp(&keys[*b]));
+        }
+        layer.instances.clear();
+        layer
+            .instances
+            .extend(layer.source_indices.iter().map(|i| entities[*i].to_raw()));
 
         if layer.entity_count() > layer.entity_maximum() || layer.entity_buffer().is_none() {
             // Allocate new buffers
             layer.entity_buffer = Some(Layer2DSystem::create_entity_buffer(
-                &entities, device, queue,
+                &layer.instances,
+                device,
+                queue,
             ));
             layer.entity_maximum = layer.entity_count * 2;
         } else {
             // Reuse buffers
-            let ents = entities.iter().map(|e| e.to_raw()).collect::<Vec<_>>();
-            let entity_data: &[u8] = bytemuck::cast_slice(ents.as_slice());
+            let entity_data: &[u8] = bytemuck::cast_slice(layer.instances.as_slice());
             queue.write_buffer(&layer.entity_buffer.as_ref().unwrap(), 0, entity_data);
         }
     }
@@ -1,9 +1,14 @@
 pub mod camera;
+pub mod capture;
+pub mod config;
 pub mod engine;
 pub mod entity;
 pub mod layer;
+pub mod picking;
 pub mod primitives;
+pub mod scene;
 pub mod texture;
 pub mod traits;
 pub mod transform;
+pub mod upscale;
 pub mod util;
@@ -0,0 +1,123 @@
+use winit::dpi::PhysicalPosition;
+
+use crate::{
+    engine::{
+        camera::camera::{Camera2D, Camera2DSystem},
+        layer::layer::{Layer2D, LayerID},
+        primitives::vector::{Vector2, Vector3},
+    },
+    event::input::context::Context2D,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct PickHit {
+    pub layer: LayerID,
+    /// Position of the entity in the slice given to `set_entities` for its layer
+    pub index: usize,
+    /// Point hit in world space
+    pub world: Vector3<f32>,
+    /// Point hit in the entity's local space, where the quad spans -0.5 to 0.5
+    pub local: Vector2<f32>,
+}
+
+// Picks from what was last set on each layer, so call after set_entities
+// to pick against the positions being rendered this frame.
+pub struct Picking2DSystem;
+
+impl Picking2DSystem {
+    /// Finds the entities under the mouse, see `pick_at`. The mouse position is in window
+    /// pixels, so with a virtual resolution use `pick_at` with the position from
+    /// `EffectSystem::window_to_render_position` instead.
+    pub fn pick(
+        ctx: &Context2D,
+        camera: &Camera2D,
+        layers: &[Layer2D],
+        ignore_transparent: bool,
+    ) -> Vec<PickHit> {
+        Picking2DSystem::pick_at(ctx.mouse_position(), camera, layers, ignore_transparent)
+    }
+
+    /// Finds every entity under a position in physical pixels of the render target,
+    /// front to back. Map the mouse with `EffectSystem::window_to_render_position` first,
+    /// when it returns None the mouse is over the bars around a virtual resolution and
+    /// nothing can be hit.
+    /// Layers later in the slice are in front, as they are when rendered.
+    /// Only layers visible to the camera are checked, and nothing is hit outside its viewport.
+    /// With `ignore_transparent` set, fully transparent texels and invisible
+    /// entities do not count as hits.
+    pub fn pick_at(
+        screen: PhysicalPosition<f64>,
+        camera: &Camera2D,
+        layers: &[Layer2D],
+        ignore_transparent: bool,
+    ) -> Vec<PickHit> {
+        let mut hits = Vec::new();
+        if !Camera2DSystem::viewport_contains(camera, screen) {
+            return hits;
+        }
+        for layer in layers.iter().rev() {
+            if !camera.is_layer_visible(layer.id()) {
+                continue;
+            }
+            for (index, instance) in layer.instances().iter().enumerate().rev() {
+                let transform = instance.transform();
+                if transform.determinant() == 0.0 {
+                    continue;
+                }
+                // Entities are flat, so the quad lies on the plane at its z
+                let world = Camera2DSystem::screen_to_world_at(camera, screen, transform.w_axis.z);
+                let local = transform
+                    .inverse()
+                    .transform_point3(glam::Vec3::new(world.x, world.y, world.z));
+
+                // Position in the texture cell, (0, 0) top left, same as the shader
+                let cell = [local.x + 0.5, 0.5 - local.y];
+                let [rect_x, rect_y, rect_width, rect_height] = instance.uv_rect();
+                let in_rect = |value: f32, start: f32, length: f32| {
+                    let (low, high) = (start.min(start + length), start.max(start + length));
+                    value >= low && value <= high
+                };
+                if !in_rect(cell[0], rect_x, rect_width) || !in_rect(cell[1], rect_y, rect_height) {
+                    continue;
+                }
+
+                if ignore_transparent {
+                    if instance.tint()[3] <= 0.0 {
+                        continue;
+                    }
+                    let flip = instance.flip();
+                    let mut sample = cell;
+                    if flip[0] {
+                        sample[0] = 2.0 * rect_x + rect_width - cell[0];
+                    }
+                    if flip[1] {
+                        sample[1] = 2.0 * rect_y + rect_height - cell[1];
+                    }
+                    let index_size = instance.texture_size();
+                    let texture_index = instance.texture_index();
+                    let tex_coords = [
+                        (texture_index[0] + sample[0]) * index_size[0],
+                        (texture_index[1] + sample[1]) * index_size[1],
+                    ];
+                    if layer.texel_alpha(tex_coords) == 0 {
+                        continue;
+                    }
+                }
+
+                hits.push(PickHit {
+                    layer: layer.id(),
+                    index: layer.source_index(index),
+                    world,
+                    local: Vector2::new(local.x, local.y),
+                });
+            }
+        }
+        hits
+    }
+
+    /// Reorders hits so the highest z comes first, matching what is drawn when the
+    /// depth buffer is enabled. Hits at the same z keep their layer order.
+    pub fn sort_by_depth(hits: &mut [PickHit]) {
+        hits.sort_by(|a, b| b.world.z.total_cmp(&a.world.z));
+    }
+}
@@ -1,3 +1,4 @@
 pub mod matrix;
+pub mod rect;
 pub mod vector;
 pub mod vertex;
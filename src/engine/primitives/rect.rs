@@ -0,0 +1,26 @@
+use num::Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect<T>
+where
+    T: Float,
+{
+    pub x: T,
+    pub y: T,
+    pub width: T,
+    pub height: T,
+}
+
+impl<T> Rect<T>
+where
+    T: Float,
+{
+    pub fn new(x: T, y: T, width: T, height: T) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
@@ -18,3 +18,21 @@ where
         Self { x, y, z }
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub struct Vector2<T>
+where
+    T: Float,
+{
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> Vector2<T>
+where
+    T: Float,
+{
+    pub fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+
+use crate::engine::{
+    entity::entity::{Entity2D, EntitySystem2D},
+    layer::layer::LayerID,
+    util::effect_error::EffectError,
+};
+
+#[derive(std::cmp::PartialEq, std::cmp::Eq, Hash, Clone, Copy, Debug, PartialOrd, Ord)]
+pub struct EntityID(pub u32);
+
+struct SceneNode {
+    entity: Entity2D,
+    parent: Option<EntityID>,
+    children: Vec<EntityID>,
+}
+
+// Entities in a scene keep their own transform relative to their parent.
+// Scene2DSystem::update works out where that puts them in the world.
+pub struct Scene2D {
+    // BTreeMap so entities are drawn in the order they were added
+    nodes: BTreeMap<EntityID, SceneNode>,
+    next_id: u32,
+}
+
+impl Scene2D {
+    pub fn new() -> Self {
+        Self {
+            nodes: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn entity(&self, id: EntityID) -> Option<&Entity2D> {
+        self.nodes.get(&id).map(|node| &node.entity)
+    }
+
+    /// Changes made here are relative to the parent, call `Scene2DSystem::update`
+    /// afterwards to move any children along with it.
+    pub fn entity_mut(&mut self, id: EntityID) -> Option<&mut Entity2D> {
+        self.nodes.get_mut(&id).map(|node| &mut node.entity)
+    }
+
+    pub fn parent(&self, id: EntityID) -> Option<EntityID> {
+        self.nodes.get(&id).and_then(|node| node.parent)
+    }
+
+    pub fn children(&self, id: EntityID) -> &[EntityID] {
+        match self.nodes.get(&id) {
+            Some(node) => node.children.as_slice(),
+            None => &[],
+        }
+    }
+
+    pub fn contains(&self, id: EntityID) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    /// The entities on the given layer, ready to be passed to `set_entities`.
+    pub fn layer_entities(&self, layer: LayerID) -> Vec<&Entity2D> {
+        self.nodes
+            .values()
+            .map(|node| &node.entity)
+            .filter(|entity| *entity.layer_id() == layer)
+            .collect()
+    }
+}
+
+impl Default for Scene2D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Scene2DSystem;
+
+impl Scene2DSystem {
+    /// Adds an entity to the scene, optionally as a child of another entity.
+    /// The entity's transform is treated as relative to its parent.
+    pub fn add_entity(
+        scene: &mut Scene2D,
+        entity: Entity2D,
+        parent: Option<EntityID>,
+    ) -> Result<EntityID> {
+        if let Some(parent) = parent {
+            if !scene.contains(parent) {
+                bail!(EffectError::new("Parent entity is not in the scene"));
+            }
+        }
+        let id = EntityID(scene.next_id);
+        scene.next_id += 1;
+        scene.nodes.insert(
+            id,
+            SceneNode {
+                entity,
+                parent,
+                children: Vec::new(),
+            },
+        );
+        if let Some(parent) = parent {
+            scene.nodes.get_mut(&parent).unwrap().children.push(id);
+        }
+        Ok(id)
+    }
+
+    /// Removes an entity from the scene. Its children are kept and become root entities.
+    pub fn remove_entity(scene: &mut Scene2D, id: EntityID) -> Option<Entity2D> {
+        let node = scene.nodes.remove(&id)?;
+        if let Some(parent) = node.parent {
+            if let Some(parent) = scene.nodes.get_mut(&parent) {
+                parent.children.retain(|child| *child != id);
+            }
+        }
+        for child in node.children.iter() {
+            if let Some(child) = scene.nodes.get_mut(child) {
+                child.parent = None;
+            }
+        }
+        let mut entity = node.entity;
+        EntitySystem2D::set_parent_transform(&mut entity, glam::Mat4::IDENTITY);
+        Some(entity)
+    }
+
+    /// Moves an entity under a new parent, or to the root of the scene with None.
+    /// The entity keeps its local transform, so it will move with its new parent.
+    pub fn set_parent(scene: &mut Scene2D, id: EntityID, parent: Option<EntityID>) -> Result<()> {
+        if !scene.contains(id) {
+            bail!(EffectError::new("Entity is not in the scene"));
+        }
+        if let Some(parent) = parent {
+            if !scene.contains(parent) {
+                bail!(EffectError::new("Parent entity is not in the scene"));
+            }
+            // Walk up from the new parent to make sure no loop is created
+            let mut ancestor = Some(parent);
+            while let Some(current) = ancestor {
+                if current == id {
+                    bail!(EffectError::new(
+                        "Entity cannot be parented to its own child"
+                    ));
+                }
+                ancestor = scene.parent(current);
+            }
+        }
+        if let Some(old_parent) = scene.parent(id) {
+            scene
+                .nodes
+                .get_mut(&old_parent)
+                .unwrap()
+                .children
+                .retain(|child| *child != id);
+        }
+        scene.nodes.get_mut(&id).unwrap().parent = parent;
+        if let Some(parent) = parent {
+            scene.nodes.get_mut(&parent).unwrap().children.push(id);
+        }
+        Ok(())
+    }
+
+    /// Propagates transforms down the tree, call once per frame after moving entities
+    /// and before setting them on their layers.
+    pub fn update(scene: &mut Scene2D) {
+        let mut stack = scene
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.parent.is_none())
+            .map(|(id, _)| (*id, glam::Mat4::IDENTITY))
+            .collect::<Vec<_>>();
+        while let Some((id, parent_transform)) = stack.pop() {
+            let node = scene.nodes.get_mut(&id).unwrap();
+            EntitySystem2D::set_parent_transform(&mut node.entity, parent_transform);
+            let world = node.entity.world_matrix();
+            stack.extend(node.children.iter().map(|child| (*child, world)));
+        }
+    }
+}
@@ -3,7 +3,7 @@ use winit::dpi::PhysicalSize;
 
 use crate::engine::{primitives::vertex::Vertex, texture::texture2d::Texture2DSystem};
 
-use super::texture2d::Texture2D;
+use super::texture2d::{AlphaMode, Texture2D};
 use anyhow::Result;
 
 // Render before layers.
@@ -29,6 +29,9 @@ pub struct EntitySpoof {
     transform: [[f32; 4]; 4],
     texture_index: [f32; 2],
     texture_size: [f32; 2],
+    tint: [f32; 4],
+    uv_rect: [f32; 4],
+    flip: [f32; 2],
 }
 
 impl Background2D {
@@ -41,7 +44,10 @@ impl Background2D {
     ) -> Result<Self> {
         let tex = image::open(texture.file_path())?;
         let dimensions = PhysicalSize::new(tex.width(), tex.height());
-        let tex_rgb = tex.into_rgba8();
+        let mut tex_rgb = tex.into_rgba8();
+        if texture.alpha_mode() == AlphaMode::Straight {
+            Texture2DSystem::premultiply(&mut tex_rgb);
+        }
         let extent = wgpu::Extent3d {
             width: dimensions.width,
             height: dimensions.height,
@@ -68,7 +74,10 @@ impl Background2D {
                 [0.0, 0.0, 0.0, 1.0],
             ],
             texture_index: [0.0, 0.0],
-            texture_size: [0.0, 0.0],
+            texture_size: [1.0, 1.0],
+            tint: [1.0, 1.0, 1.0, 1.0],
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            flip: [0.0, 0.0],
         };
 
         // Rust automatically assumes f64
@@ -4,6 +4,16 @@ use image::Rgba;
 #[derive(std::cmp::PartialEq, std::cmp::Eq, Hash, Clone, Debug, Copy)]
 pub struct TextureID(pub &'static str);
 
+/// How the colour in an image file relates to its alpha.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum AlphaMode {
+    /// Colour is independent of alpha, as in most PNGs. Premultiplied when loaded.
+    #[default]
+    Straight,
+    /// Colour is already multiplied by alpha, uploaded as is
+    Premultiplied,
+}
+
 #[derive(Clone, Debug)]
 pub struct Texture2D {
     id: TextureID,
@@ -11,6 +21,7 @@ pub struct Texture2D {
     width: u32,
     height: u32,
     index: Option<[u32; 2]>,
+    alpha_mode: AlphaMode,
 }
 
 impl Texture2D {
@@ -21,6 +32,7 @@ impl Texture2D {
             width: 0,
             height: 0,
             index: None,
+            alpha_mode: AlphaMode::Straight,
         }
     }
 
@@ -43,6 +55,10 @@ impl Texture2D {
     pub fn height(&self) -> u32 {
         self.height
     }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
 }
 
 pub struct Texture2DSystem;
@@ -56,6 +72,42 @@ impl Texture2DSystem {
         texture.height = height;
     }
 
+    /// Set before the texture is given to a layer or background.
+    pub fn set_alpha_mode(texture: &mut Texture2D, alpha_mode: AlphaMode) {
+        texture.alpha_mode = alpha_mode;
+    }
+
+    /// Multiplies colour by alpha, so filtering and blending don't bring out the colour
+    /// of transparent texels as dark fringes. Done in linear space as the GPU samples
+    /// the sRGB texture in linear space.
+    pub fn premultiply(rgba_image: &mut ImageBuffer<Rgba<u8>, Vec<u8>>) {
+        let to_linear = |value: f32| {
+            if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let to_srgb = |value: f32| {
+            if value <= 0.0031308 {
+                value * 12.92
+            } else {
+                1.055 * value.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        for pixel in rgba_image.pixels_mut() {
+            let alpha = pixel[3];
+            if alpha == 255 {
+                continue;
+            }
+            let alpha = alpha as f32 / 255.0;
+            for channel in pixel.0.iter_mut().take(3) {
+                let linear = to_linear(*channel as f32 / 255.0) * alpha;
+                *channel = (to_srgb(linear) * 255.0).round() as u8;
+            }
+        }
+    }
+
     pub fn init_texture(
         extent: wgpu::Extent3d,
         rgba_image: ImageBuffer<Rgba<u8>, Vec<u8>>,
@@ -3,7 +3,7 @@ use crate::engine::{texture::texture2d::Texture2DSystem, util::effect_error::Eff
 use image::{GenericImage, ImageBuffer};
 use winit::dpi::PhysicalSize;
 
-use super::texture2d::Texture2D;
+use super::texture2d::{AlphaMode, Texture2D};
 
 use anyhow::{bail, Result};
 
@@ -14,6 +14,8 @@ pub struct TextureAtlas2D {
     bind_group: wgpu::BindGroup,
     dimensions: PhysicalSize<u32>,
     tex_coord_size: PhysicalSize<f32>,
+    // CPU copy of the alpha channel, used to ignore transparent texels when picking
+    alpha_mask: Vec<u8>,
 }
 
 impl TextureAtlas2D {
@@ -46,7 +48,10 @@ impl TextureAtlas2D {
                 texture_size.height,
                 image::imageops::FilterType::Lanczos3,
             );
-            let tex_rgba = tex.to_rgba8();
+            let mut tex_rgba = tex.to_rgba8();
+            if texture.alpha_mode() == AlphaMode::Straight {
+                Texture2DSystem::premultiply(&mut tex_rgba);
+            }
             Texture2DSystem::set_dimensions(texture, texture_size.width, texture_size.height);
 
             let pot_width = current_width + texture_size.width;
@@ -89,6 +94,7 @@ impl TextureAtlas2D {
             depth_or_array_layers: 1,
         };
 
+        let alpha_mask = combined_tex.pixels().map(|pixel| pixel[3]).collect();
         let bind_group = Texture2DSystem::init_texture(
             extent,
             combined_tex,
@@ -105,6 +111,7 @@ impl TextureAtlas2D {
             bind_group,
             dimensions,
             tex_coord_size,
+            alpha_mask,
         })
     }
 
@@ -119,4 +126,13 @@ impl TextureAtlas2D {
     pub fn tex_coord_size(&self) -> PhysicalSize<f32> {
         self.tex_coord_size
     }
+
+    /// Alpha of the texel at the given atlas texture coordinates, (0, 0) to (1, 1).
+    pub fn alpha(&self, tex_coords: [f32; 2]) -> u8 {
+        let width = self.dimensions.width;
+        let height = self.dimensions.height;
+        let x = ((tex_coords[0] * width as f32) as u32).min(width - 1);
+        let y = ((tex_coords[1] * height as f32) as u32).min(height - 1);
+        self.alpha_mask[(y * width + x) as usize]
+    }
 }
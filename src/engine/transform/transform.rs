@@ -1,44 +1,84 @@
-use crate::engine::primitives::{matrix::Matrix4, vector::Vector3};
+use crate::engine::primitives::{
+    matrix::Matrix4,
+    vector::{Vector2, Vector3},
+};
 
+// Forward is the local up axis, so a sprite drawn facing up points along forward
+// when its rotation is 0.
 pub struct Transform2D {
-    matrix: Matrix4,
+    matrix: glam::Mat4,
     rotation: f32,
-    scale: f32,
-    position: Vector3<f32>,
+    scale: glam::Vec2,
+    pivot: glam::Vec2,
+    position: glam::Vec3,
 }
 
 impl Transform2D {
     pub fn new() -> Self {
-        let matrix = Matrix4::new();
-        let rotation = 0.0;
-        let scale = 1.0;
-        let position = Vector3 {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
-        };
         Self {
-            matrix,
-            rotation,
-            scale,
-            position,
+            matrix: glam::Mat4::IDENTITY,
+            rotation: 0.0,
+            scale: glam::Vec2::ONE,
+            pivot: glam::Vec2::ZERO,
+            position: glam::Vec3::ZERO,
         }
     }
 
     pub fn to_raw(&self) -> Matrix4 {
+        Matrix4::from_slice(self.matrix.to_cols_array_2d())
+    }
+
+    pub fn matrix(&self) -> glam::Mat4 {
         self.matrix
     }
 
-    pub fn position(&self) -> &Vector3<f32> {
-        &self.position
+    pub fn position(&self) -> Vector3<f32> {
+        Vector3::new(self.position.x, self.position.y, self.position.z)
     }
 
     pub fn rotation(&self) -> f32 {
         self.rotation.to_degrees()
     }
 
-    pub fn scale(&self) -> f32 {
-        self.scale
+    pub fn scale(&self) -> Vector2<f32> {
+        Vector2::new(self.scale.x, self.scale.y)
+    }
+
+    pub fn pivot(&self) -> Vector2<f32> {
+        Vector2::new(self.pivot.x, self.pivot.y)
+    }
+
+    /// Unit vector the transform is facing, its local up axis in world space.
+    pub fn forward(&self) -> Vector2<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        Vector2::new(-sin, cos)
+    }
+
+    /// Unit vector to the right of forward, the local x axis in world space.
+    pub fn right(&self) -> Vector2<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        Vector2::new(cos, sin)
+    }
+
+    /// Converts a point in local space, where the quad spans -0.5 to 0.5, to world space.
+    pub fn local_to_world(&self, point: Vector3<f32>) -> Vector3<f32> {
+        let world = self
+            .matrix
+            .transform_point3(glam::Vec3::new(point.x, point.y, point.z));
+        Vector3::new(world.x, world.y, world.z)
+    }
+
+    /// Converts a world space point into local space. Returns the pivot's position
+    /// if the transform has a zero scale and cannot be inverted.
+    pub fn world_to_local(&self, point: Vector3<f32>) -> Vector3<f32> {
+        if self.scale.x == 0.0 || self.scale.y == 0.0 {
+            return Vector3::new(self.pivot.x, self.pivot.y, 0.0);
+        }
+        let local = self
+            .matrix
+            .inverse()
+            .transform_point3(glam::Vec3::new(point.x, point.y, point.z));
+        Vector3::new(local.x, local.y, local.z)
     }
 }
 
@@ -47,28 +87,153 @@ pub struct Transform2DSystem;
 impl Transform2DSystem {
     pub fn rotate(transform: &mut Transform2D, degrees: f32) {
         let degrees = degrees % 360.0;
-        let radians = degrees.to_radians();
-        transform.rotation = radians;
-        transform.matrix.inner[0][0] = radians.cos() * transform.scale;
-        transform.matrix.inner[0][1] = radians.sin() * transform.scale;
-        transform.matrix.inner[1][0] = -(radians.sin()) * transform.scale;
-        transform.matrix.inner[1][1] = radians.cos() * transform.scale;
+        transform.rotation = degrees.to_radians();
+        Transform2DSystem::update_matrix(transform);
+    }
+
+    pub fn rotate_by(transform: &mut Transform2D, degrees: f32) {
+        let current = transform.rotation.to_degrees();
+        Transform2DSystem::rotate(transform, current + degrees);
     }
 
     pub fn translate(transform: &mut Transform2D, position: Vector3<f32>) {
-        transform.position = position;
-        transform.matrix.inner[3][0] = position.x * transform.rotation.cos() * transform.scale
-            + position.y * (-(transform.rotation.sin())) * transform.scale;
-        transform.matrix.inner[3][1] = position.x * transform.rotation.sin() * transform.scale
-            + position.y * transform.rotation.cos() * transform.scale;
-        transform.matrix.inner[3][2] = position.z;
+        transform.position = glam::Vec3::new(position.x, position.y, position.z);
+        Transform2DSystem::update_matrix(transform);
+    }
+
+    pub fn translate_by(transform: &mut Transform2D, offset: Vector3<f32>) {
+        transform.position += glam::Vec3::new(offset.x, offset.y, offset.z);
+        Transform2DSystem::update_matrix(transform);
     }
 
     pub fn scale(transform: &mut Transform2D, scale: f32) {
-        transform.scale = scale;
-        transform.matrix.inner[0][0] = transform.rotation.cos() * scale;
-        transform.matrix.inner[0][1] = transform.rotation.sin() * scale;
-        transform.matrix.inner[1][0] = -(transform.rotation.sin()) * scale;
-        transform.matrix.inner[1][1] = transform.rotation.cos() * scale;
+        Transform2DSystem::scale_xy(transform, Vector2::new(scale, scale));
+    }
+
+    /// Scales the width and height independently, eg for squash and stretch.
+    pub fn scale_xy(transform: &mut Transform2D, scale: Vector2<f32>) {
+        transform.scale = glam::Vec2::new(scale.x, scale.y);
+        Transform2DSystem::update_matrix(transform);
+    }
+
+    /// Sets the point the transform rotates and scales around, which is also the point
+    /// placed at the position. It is given in the entity's local space, where the quad spans
+    /// -0.5 to 0.5 on each axis. (0, 0) is the centre, (0, -0.5) the bottom centre
+    /// and (-0.5, 0) the left edge.
+    pub fn set_pivot(transform: &mut Transform2D, pivot: Vector2<f32>) {
+        transform.pivot = glam::Vec2::new(pivot.x, pivot.y);
+        Transform2DSystem::update_matrix(transform);
+    }
+
+    /// Rotates the transform so forward points at the target.
+    /// Does nothing if the target is at the transform's position.
+    pub fn look_at(transform: &mut Transform2D, target: Vector2<f32>) {
+        let direction = glam::Vec2::new(target.x, target.y) - transform.position.truncate();
+        if direction.length_squared() == 0.0 {
+            return;
+        }
+        let degrees = direction.y.atan2(direction.x).to_degrees() - 90.0;
+        Transform2DSystem::rotate(transform, degrees);
+    }
+
+    fn update_matrix(transform: &mut Transform2D) {
+        transform.matrix = glam::Mat4::from_translation(transform.position)
+            * glam::Mat4::from_rotation_z(transform.rotation)
+            * glam::Mat4::from_scale(transform.scale.extend(1.0))
+            * glam::Mat4::from_translation(-transform.pivot.extend(0.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: Vector3<f32>, expected: [f32; 3]) {
+        let actual = [actual.x, actual.y, actual.z];
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < EPSILON, "{actual:?} != {expected:?}");
+        }
+    }
+
+    fn assert_close_2d(actual: Vector2<f32>, expected: [f32; 2]) {
+        assert!(
+            (actual.x - expected[0]).abs() < EPSILON && (actual.y - expected[1]).abs() < EPSILON,
+            "({}, {}) != {expected:?}",
+            actual.x,
+            actual.y
+        );
+    }
+
+    #[test]
+    fn translate_keeps_position_when_rotated_and_scaled() {
+        let mut transform = Transform2D::new();
+        Transform2DSystem::rotate(&mut transform, 45.0);
+        Transform2DSystem::scale_xy(&mut transform, Vector2::new(2.0, 3.0));
+        Transform2DSystem::translate(&mut transform, Vector3::new(1.0, 2.0, 0.5));
+        Transform2DSystem::translate_by(&mut transform, Vector3::new(0.5, -1.0, 0.0));
+
+        assert_close(transform.position(), [1.5, 1.0, 0.5]);
+        // The centre of the quad is the default pivot, so it lands on the position
+        assert_close(
+            transform.local_to_world(Vector3::new(0.0, 0.0, 0.0)),
+            [1.5, 1.0, 0.5],
+        );
+    }
+
+    #[test]
+    fn pivot_is_placed_at_position() {
+        let mut transform = Transform2D::new();
+        Transform2DSystem::set_pivot(&mut transform, Vector2::new(0.0, -0.5));
+        Transform2DSystem::scale(&mut transform, 2.0);
+        Transform2DSystem::rotate(&mut transform, 90.0);
+        Transform2DSystem::translate(&mut transform, Vector3::new(3.0, 4.0, 0.0));
+
+        assert_close(
+            transform.local_to_world(Vector3::new(0.0, -0.5, 0.0)),
+            [3.0, 4.0, 0.0],
+        );
+        // The top centre is one scaled quad height away from the pivot, along forward
+        assert_close(
+            transform.local_to_world(Vector3::new(0.0, 0.5, 0.0)),
+            [1.0, 4.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn forward_and_right_follow_rotation() {
+        let mut transform = Transform2D::new();
+        assert_close_2d(transform.forward(), [0.0, 1.0]);
+        assert_close_2d(transform.right(), [1.0, 0.0]);
+
+        Transform2DSystem::rotate(&mut transform, 90.0);
+        assert_close_2d(transform.forward(), [-1.0, 0.0]);
+        assert_close_2d(transform.right(), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn look_at_points_forward_at_target() {
+        let mut transform = Transform2D::new();
+        Transform2DSystem::translate(&mut transform, Vector3::new(1.0, 1.0, 0.0));
+        Transform2DSystem::look_at(&mut transform, Vector2::new(4.0, 5.0));
+        assert_close_2d(transform.forward(), [0.6, 0.8]);
+
+        let rotation = transform.rotation();
+        Transform2DSystem::look_at(&mut transform, Vector2::new(1.0, 1.0));
+        assert_eq!(transform.rotation(), rotation);
+    }
+
+    #[test]
+    fn world_to_local_inverts_local_to_world() {
+        let mut transform = Transform2D::new();
+        Transform2DSystem::set_pivot(&mut transform, Vector2::new(-0.5, 0.25));
+        Transform2DSystem::scale_xy(&mut transform, Vector2::new(1.5, 0.5));
+        Transform2DSystem::rotate(&mut transform, 33.0);
+        Transform2DSystem::translate(&mut transform, Vector3::new(-2.0, 7.0, 0.25));
+
+        let point = Vector3::new(0.3, -0.4, 0.0);
+        let round_trip = transform.world_to_local(transform.local_to_world(point));
+        assert_close(round_trip, [0.3, -0.4, 0.0]);
     }
 }
@@ -0,0 +1,271 @@
+use anyhow::{bail, Result};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use crate::engine::{primitives::rect::Rect, util::effect_error::EffectError};
+
+/// Low resolution texture every layer is rendered into before it is scaled up
+/// to the window, so pixel art stays the same size and doesn't shimmer.
+pub struct VirtualTarget {
+    size: PhysicalSize<u32>,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+pub struct Upscaler {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    target: Option<VirtualTarget>,
+}
+
+impl Upscaler {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader_module =
+            device.create_shader_module(wgpu::include_wgsl!("../../shaders/upscale.wgsl"));
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("Upscale bind group layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Upscale pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Upscale pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vrt_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: "frg_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+        // Nearest so every virtual pixel becomes a solid block
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            format,
+            target: None,
+        }
+    }
+
+    pub fn virtual_size(&self) -> Option<PhysicalSize<u32>> {
+        self.target.as_ref().map(|target| target.size)
+    }
+
+    /// View to render the layers into, None when rendering straight to the window.
+    pub fn target_view(&self) -> Option<&wgpu::TextureView> {
+        self.target.as_ref().map(|target| &target.view)
+    }
+}
+
+pub struct UpscalerSystem;
+impl UpscalerSystem {
+    /// Renders at a fixed resolution, scaled to the window by the largest whole number
+    /// that fits. None renders at the window's resolution.
+    pub fn set_virtual_resolution(
+        upscaler: &mut Upscaler,
+        device: &wgpu::Device,
+        size: Option<PhysicalSize<u32>>,
+    ) -> Result<()> {
+        let size = match size {
+            Some(size) => size,
+            None => {
+                upscaler.target = None;
+                return Ok(());
+            }
+        };
+        if size.width == 0 || size.height == 0 {
+            bail!(EffectError::new("Virtual resolution must be non zero"));
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Virtual target"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: upscaler.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Virtual target bind group"),
+            layout: &upscaler.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&upscaler.sampler),
+                },
+            ],
+        });
+        upscaler.target = Some(VirtualTarget {
+            size,
+            view,
+            bind_group,
+        });
+        Ok(())
+    }
+
+    /// Where the virtual target ends up in the window, in physical pixels.
+    /// Falls back to fitting without whole number scaling when the window is
+    /// smaller than the virtual resolution.
+    pub fn scaled_rect(
+        virtual_size: PhysicalSize<u32>,
+        window_size: PhysicalSize<u32>,
+    ) -> Rect<f32> {
+        let scale_x = window_size.width as f32 / virtual_size.width as f32;
+        let scale_y = window_size.height as f32 / virtual_size.height as f32;
+        let fit = scale_x.min(scale_y);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        // Rounding can push the fitted side a fraction past the window, which wgpu rejects
+        let width = (virtual_size.width as f32 * scale).min(window_size.width as f32);
+        let height = (virtual_size.height as f32 * scale).min(window_size.height as f32);
+        Rect::new(
+            ((window_size.width as f32 - width) / 2.0).floor(),
+            ((window_size.height as f32 - height) / 2.0).floor(),
+            width,
+            height,
+        )
+    }
+
+    /// Converts a position in the window, eg the mouse, to the virtual resolution.
+    /// None when the position is over the bars.
+    pub fn window_to_virtual(
+        upscaler: &Upscaler,
+        window_size: PhysicalSize<u32>,
+        position: PhysicalPosition<f64>,
+    ) -> Option<PhysicalPosition<f64>> {
+        let virtual_size = match upscaler.virtual_size() {
+            Some(size) => size,
+            None => return Some(position),
+        };
+        let rect = UpscalerSystem::scaled_rect(virtual_size, window_size);
+        let x = (position.x - rect.x as f64) / rect.width as f64;
+        let y = (position.y - rect.y as f64) / rect.height as f64;
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return None;
+        }
+        Some(PhysicalPosition::new(
+            x * virtual_size.width as f64,
+            y * virtual_size.height as f64,
+        ))
+    }
+
+    /// Draws the virtual target onto the window's texture, filling the bars with black.
+    pub fn upscale(
+        upscaler: &Upscaler,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture_view: &wgpu::TextureView,
+        window_size: PhysicalSize<u32>,
+    ) {
+        let target = match upscaler.target.as_ref() {
+            Some(target) => target,
+            None => return,
+        };
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upscale pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let rect = UpscalerSystem::scaled_rect(target.size, window_size);
+        render_pass.set_viewport(rect.x, rect.y, rect.width, rect.height, 0.0, 1.0);
+        render_pass.set_pipeline(&upscaler.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_rect_uses_whole_number_scale() {
+        let rect =
+            UpscalerSystem::scaled_rect(PhysicalSize::new(320, 180), PhysicalSize::new(1000, 600));
+        assert_eq!(
+            (rect.x, rect.y, rect.width, rect.height),
+            (20.0, 30.0, 960.0, 540.0)
+        );
+    }
+
+    #[test]
+    fn scaled_rect_fits_inside_smaller_window() {
+        let window_size = PhysicalSize::new(241, 241);
+        let rect = UpscalerSystem::scaled_rect(PhysicalSize::new(1920, 1080), window_size);
+        assert!(rect.x >= 0.0 && rect.y >= 0.0);
+        assert!(rect.x + rect.width <= window_size.width as f32);
+        assert!(rect.y + rect.height <= window_size.height as f32);
+    }
+}
@@ -20,3 +20,46 @@ impl EffectError {
         }
     }
 }
+
+/// Reasons the window or engine could not be created, returned by `init_engine`.
+#[derive(Debug)]
+pub enum EngineError {
+    InvalidDimensions,
+    EventLoop(winit::error::EventLoopError),
+    Window(winit::error::OsError),
+    InvalidIcon(String),
+    CreateSurface(wgpu::CreateSurfaceError),
+    /// No adapter matched the backends, and software fallback was not allowed
+    NoAdapter,
+    /// No usable adapter's name contains `EngineConfig::adapter_name`
+    AdapterNotFound(String),
+    /// The adapter lacks features asked for in `EngineConfig::required_features`
+    MissingFeatures(wgpu::Features),
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::InvalidDimensions => write!(f, "Effect 2: Dimensions must be non zero"),
+            EngineError::EventLoop(error) => write!(f, "Effect 2: Event loop error: {}", error),
+            EngineError::Window(error) => write!(f, "Effect 2: Window error: {}", error),
+            EngineError::InvalidIcon(msg) => write!(f, "Effect 2: Invalid icon: {}", msg),
+            EngineError::CreateSurface(error) => {
+                write!(f, "Effect 2: Could not create surface: {}", error)
+            }
+            EngineError::NoAdapter => write!(f, "Effect 2: No suitable adapter found"),
+            EngineError::AdapterNotFound(name) => {
+                write!(f, "Effect 2: No adapter found named {}", name)
+            }
+            EngineError::MissingFeatures(features) => {
+                write!(f, "Effect 2: Adapter is missing features {:?}", features)
+            }
+            EngineError::RequestDevice(error) => {
+                write!(f, "Effect 2: Could not create device: {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
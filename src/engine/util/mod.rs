@@ -1,2 +1,3 @@
 pub mod effect_error;
 pub mod file_to_bytes;
+pub mod readback;
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 
 use winit::{
-    dpi::PhysicalPosition,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::{DeviceEvent, ElementState, Event, MouseButton, WindowEvent},
     keyboard::{KeyCode, PhysicalKey},
 };
@@ -16,6 +16,7 @@ pub struct Context2D {
     mouse_within_window: bool,
     mouse_position: PhysicalPosition<f64>,
     mouse_travel: (f64, f64),
+    resized: Option<PhysicalSize<u32>>,
 }
 
 impl Context2D {
@@ -27,6 +28,7 @@ impl Context2D {
         let mouse_within_window = false;
         let mouse_position = PhysicalPosition::new(0.0, 0.0);
         let mouse_travel = (0.0, 0.0);
+        let resized = None;
         Self {
             keys_pressed,
             keys_released,
@@ -35,6 +37,7 @@ impl Context2D {
             mouse_within_window,
             mouse_position,
             mouse_travel,
+            resized,
         }
     }
 
@@ -61,6 +64,11 @@ impl Context2D {
     pub fn mouse_delta(&self) -> (f64, f64) {
         self.mouse_travel
     }
+
+    /// The new size of the window if it was resized since the previous frame.
+    pub fn resized(&self) -> Option<PhysicalSize<u32>> {
+        self.resized
+    }
 }
 
 pub struct Context2DSystem;
@@ -112,6 +120,9 @@ impl Context2DSystem {
                 WindowEvent::CursorLeft { device_id } => {
                     context.mouse_within_window = false;
                 }
+                WindowEvent::Resized(size) => {
+                    context.resized = Some(*size);
+                }
                 _ => (),
             },
             Event::DeviceEvent { device_id, event } => match event {
@@ -128,4 +139,8 @@ impl Context2DSystem {
         context.keys_released.clear();
         context.mouse_released.clear();
     }
+
+    pub fn clear_resized(context: &mut Context2D) {
+        context.resized = None;
+    }
 }
@@ -32,6 +32,7 @@ impl EffectSystem {
                     user_loop(&ctx, after - before, control);
                     before = after;
                     Context2DSystem::clear_released(&mut ctx);
+                    Context2DSystem::clear_resized(&mut ctx);
                 }
                 _ => (),
             }
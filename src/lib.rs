@@ -1,19 +1,27 @@
 pub mod engine;
 pub mod event;
 pub mod sound;
-use std::time::{Duration, Instant};
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
 use engine::{
-    camera::camera::Camera2D,
-    engine as effect,
+    camera::camera::{Camera2D, CameraProjection},
+    config::config::{EngineConfig, WindowMode},
+    engine::{self as effect, FrameStatus},
     entity::entity::Entity2D,
     layer::layer::{Layer2D, LayerID},
     texture::texture2d::{Texture2D, TextureID},
+    util::effect_error::EngineError,
 };
 use event::input::context::Context2D;
+use image::RgbaImage;
 use winit::{
-    dpi::PhysicalSize,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::{ElementState, Event, WindowEvent},
     event_loop::{self, ControlFlow, EventLoop},
     keyboard::KeyCode,
@@ -25,32 +33,118 @@ pub struct EffectSystem {
 }
 
 impl EffectSystem {
-    pub fn new(
-        screen_dimensions: PhysicalSize<u32>,
-        camera_fov: f32,
-        v_sync: bool,
-    ) -> (Self, EventLoop<()>) {
-        let event_loop = EventLoop::new().unwrap();
+    pub fn new(config: EngineConfig) -> Result<(Self, EventLoop<()>), EngineError> {
+        let event_loop = EventLoop::new().map_err(EngineError::EventLoop)?;
         event_loop.set_control_flow(ControlFlow::Poll);
+        let fullscreen = config
+            .window_mode
+            .to_fullscreen(event_loop.primary_monitor());
         let window = WindowBuilder::new()
-            .with_title("Effect Engine")
-            .with_inner_size(screen_dimensions)
-            .with_resizable(false)
+            .with_title(config.title.clone())
+            .with_inner_size(config.dimensions)
+            .with_resizable(config.resizable)
+            .with_decorations(config.decorations)
+            .with_fullscreen(fullscreen)
+            .with_window_icon(config.load_icon()?)
             .build(&event_loop)
-            .unwrap();
-        let engine = pollster::block_on(effect::Engine::new(window, camera_fov, v_sync));
-        (Self { engine }, event_loop)
+            .map_err(EngineError::Window)?;
+        let engine = pollster::block_on(effect::Engine::new(window, &config))?;
+        Ok((Self { engine }, event_loop))
+    }
+
+    /// Creates a system without a window, rendering into an offscreen texture.
+    /// Use `render_to_image` to get the rendered frames back.
+    pub fn new_headless(dimensions: PhysicalSize<u32>) -> Result<Self, EngineError> {
+        EffectSystem::new_headless_with_config(EngineConfig::new().with_dimensions(dimensions))
+    }
+
+    /// Headless, using the config's dimensions, MSAA, adapter and clear colour.
+    /// Window settings are ignored.
+    pub fn new_headless_with_config(config: EngineConfig) -> Result<Self, EngineError> {
+        let engine = pollster::block_on(effect::Engine::new_headless(&config))?;
+        Ok(Self { engine })
+    }
+
+    /// Every adapter usable with the given backends, their names can be passed to
+    /// `EngineConfig::with_adapter_name`.
+    pub fn available_adapters(backends: wgpu::Backends) -> Vec<wgpu::AdapterInfo> {
+        effect::Engine::available_adapters(backends)
     }
 
     /// it is up to the user to sort the layers, they have the tools to do so.
+    /// Surface problems such as a lost or outdated surface are recovered from by
+    /// skipping the frame, check the returned status to react to them.
+    /// Only `wgpu::SurfaceError::OutOfMemory` is returned as an error.
     pub fn render(
         &mut self,
         layers: &Vec<Layer2D>,
         camera: &Camera2D,
-    ) -> Result<(), wgpu::SurfaceError> {
+    ) -> Result<FrameStatus, wgpu::SurfaceError> {
         self.engine.render(&layers, camera)
     }
 
+    /// Renders the layers offscreen and returns the frame, rather than presenting it.
+    pub fn render_to_image(
+        &mut self,
+        layers: &Vec<Layer2D>,
+        camera: &Camera2D,
+    ) -> Result<RgbaImage> {
+        self.engine.render_to_image(layers, camera)
+    }
+
+    /// Renders the layers through several cameras in one frame, eg for split screen.
+    /// Each camera draws into its own viewport, see `Camera2DSystem::set_viewport`.
+    pub fn render_views(
+        &mut self,
+        layers: &Vec<Layer2D>,
+        cameras: &[&Camera2D],
+    ) -> Result<FrameStatus, wgpu::SurfaceError> {
+        self.engine.render_views(layers, cameras)
+    }
+
+    pub fn render_views_to_image(
+        &mut self,
+        layers: &Vec<Layer2D>,
+        cameras: &[&Camera2D],
+    ) -> Result<RgbaImage> {
+        self.engine.render_views_to_image(layers, cameras)
+    }
+
+    /// Copies the next rendered frame to the CPU, retrieve it with `take_captured_frame`
+    /// after calling `render`.
+    pub fn capture_next_frame(&mut self) {
+        self.engine.capture_next_frame();
+    }
+
+    pub fn take_captured_frame(&mut self) -> Option<RgbaImage> {
+        self.engine.take_captured_frame()
+    }
+
+    /// Saves the next rendered frame as a PNG.
+    pub fn save_screenshot(&mut self, path: impl Into<PathBuf>) {
+        self.engine.save_screenshot(path);
+    }
+
+    /// Saves every `interval`th rendered frame into `directory` as numbered PNG files,
+    /// until `stop_frame_capture` is called.
+    pub fn start_frame_capture(
+        &mut self,
+        interval: u32,
+        directory: impl Into<PathBuf>,
+    ) -> Result<()> {
+        self.engine.start_frame_capture(interval, directory)
+    }
+
+    pub fn stop_frame_capture(&mut self) {
+        self.engine.stop_frame_capture();
+    }
+
+    /// Captures happen while rendering, so any failure to read back or save a frame
+    /// is kept until it is taken here.
+    pub fn take_capture_error(&mut self) -> Option<anyhow::Error> {
+        self.engine.take_capture_error()
+    }
+
     /// Make sure your texture_size is set to the larger dimension that appears in your textures.
     /// It would be easier to use textures which all have the same dimensions
     /// and set that to the texture size, otherwise 2D transformations may not
@@ -78,7 +172,99 @@ impl EffectSystem {
         self.engine.init_camera(fov)
     }
 
-    pub fn update_camera(&self, camera: &mut Camera2D) {
+    /// Creates a camera with the given projection, eg orthographic for pixel exact placement.
+    /// The projection can be changed later with `Camera2DSystem::set_projection`.
+    pub fn init_camera_with_projection(&self, projection: CameraProjection) -> Camera2D {
+        self.engine.init_camera_with_projection(projection)
+    }
+
+    /// Size of the render target, follows the window when it is resized.
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.engine.size()
+    }
+
+    /// Resizes the render target, only needed when headless as window resizes are
+    /// picked up by `update_camera` and `render`. Cameras adapt on their next `update_camera`.
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.engine.resize(size);
+    }
+
+    /// Colour the frame is cleared to before the background and layers are drawn.
+    pub fn set_clear_colour(&mut self, clear_colour: wgpu::Color) {
+        self.engine.set_clear_colour(clear_colour);
+    }
+
+    pub fn clear_colour(&self) -> wgpu::Color {
+        self.engine.clear_colour()
+    }
+
+    pub fn set_window_mode(&mut self, window_mode: WindowMode) {
+        self.engine.set_window_mode(window_mode);
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        self.engine.window_mode()
+    }
+
+    /// Changes the present mode, eg Fifo, Mailbox or Immediate. The surface is reconfigured
+    /// at the start of the next `render`, so this is safe to call at any point in a frame.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) -> Result<()> {
+        self.engine.set_present_mode(present_mode)
+    }
+
+    pub fn set_v_sync(&mut self, v_sync: bool) -> Result<()> {
+        match v_sync {
+            true => self.set_present_mode(wgpu::PresentMode::AutoVsync),
+            false => self.set_present_mode(wgpu::PresentMode::AutoNoVsync),
+        }
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.engine.present_mode()
+    }
+
+    /// Present modes that can be passed to `set_present_mode`, besides the Auto modes.
+    pub fn supported_present_modes(&self) -> &[wgpu::PresentMode] {
+        self.engine.supported_present_modes()
+    }
+
+    /// Maximum number of frames queued ahead, applied from the next `render`.
+    pub fn set_frame_latency(&mut self, frame_latency: u32) -> Result<()> {
+        self.engine.set_frame_latency(frame_latency)
+    }
+
+    pub fn frame_latency(&self) -> u32 {
+        self.engine.frame_latency()
+    }
+
+    /// Renders at a fixed resolution, eg 320x180, scaled up to the window in whole
+    /// number steps with bars around it. Cameras fit the virtual resolution.
+    pub fn set_virtual_resolution(&mut self, size: Option<PhysicalSize<u32>>) -> Result<()> {
+        self.engine.set_virtual_resolution(size)
+    }
+
+    pub fn virtual_resolution(&self) -> Option<PhysicalSize<u32>> {
+        self.engine.virtual_resolution()
+    }
+
+    /// Converts a window position, eg `Context2D::mouse_position`, to the resolution
+    /// cameras render at. None when it is over the bars of a virtual resolution.
+    pub fn window_to_render_position(
+        &self,
+        position: PhysicalPosition<f64>,
+    ) -> Option<PhysicalPosition<f64>> {
+        self.engine.window_to_render_position(position)
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        if let Some(window) = self.engine.window() {
+            window.set_resizable(resizable);
+        }
+    }
+
+    /// Fits the camera to the current render target size, according to its resize policy,
+    /// and uploads it. Call after moving the camera and before `render`.
+    pub fn update_camera(&mut self, camera: &mut Camera2D) {
         self.engine.update_camera(camera);
     }
 
@@ -94,15 +280,15 @@ impl EffectSystem {
         self.engine.device()
     }
 
-    pub fn surface(&self) -> &wgpu::Surface {
+    pub fn surface(&self) -> Option<&wgpu::Surface> {
         self.engine.surface()
     }
 }
 
-pub fn init_engine(
-    screen_dimensions: PhysicalSize<u32>,
-    camera_fov: f32,
-    v_sync: bool,
-) -> (EffectSystem, EventLoop<()>) {
-    EffectSystem::new(screen_dimensions, camera_fov, v_sync)
+pub fn init_engine(config: EngineConfig) -> Result<(EffectSystem, EventLoop<()>), EngineError> {
+    EffectSystem::new(config)
+}
+
+pub fn init_engine_headless(dimensions: PhysicalSize<u32>) -> Result<EffectSystem, EngineError> {
+    EffectSystem::new_headless(dimensions)
 }
@@ -7,6 +7,7 @@ use std::{
 use effect_engine::{
     engine::{
         camera::camera::{Camera2D, Camera2DSystem, CameraAction},
+        config::config::EngineConfig,
         entity::entity::Entity2D,
         layer::layer::LayerID,
         primitives::vector::Vector3,
@@ -50,7 +51,7 @@ fn main() {
 // or move the camera only when an entity reaches the edge, etc
 fn camera_example() {
     let (mut app, event_loop) =
-        effect_engine::init_engine(PhysicalSize::new(800, 600), 45.0, false);
+        effect_engine::init_engine(EngineConfig::new().with_v_sync(false)).unwrap();
     let bg_id = TextureID("grass");
     let bg = Texture2D::new(bg_id, "grass_bg_small.png");
     app.set_background(bg, true).unwrap();
@@ -102,7 +103,7 @@ fn sound_example() {
     http://creativecommons.org/licenses/by/4.0/
     */
     let (mut app, event_loop) =
-        effect_engine::init_engine(PhysicalSize::new(800, 600), 45.0, false);
+        effect_engine::init_engine(EngineConfig::new().with_v_sync(false)).unwrap();
     let mut cam = app.init_camera(45.0);
     let layers = Vec::new();
     let mut mixer = Mixer::new();
@@ -0,0 +1,12 @@
+// Fills the viewport with the blend constant, which is set to the clear colour
+@vertex
+fn vrt_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle large enough to cover the whole viewport, at the far plane
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 1.0, 1.0);
+}
+
+@fragment
+fn frg_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
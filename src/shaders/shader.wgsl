@@ -6,6 +6,9 @@ struct EntityInput {
     @location(5) model_d: vec4<f32>,
     @location(6) index: vec2<f32>,
     @location(7) size: vec2<f32>,
+    @location(8) tint: vec4<f32>,
+    @location(9) uv_rect: vec4<f32>,
+    @location(10) flip: vec2<f32>,
 }
 
 struct VertexInput {
@@ -16,6 +19,7 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) tint: vec4<f32>,
 }
 
 struct Camera {
@@ -37,9 +41,17 @@ fn vrt_main(
         entity.model_c,
         entity.model_d,  
     );
-    out.tex_coords = vec2<f32>(model.tex_coords.x + (entity.index.x * entity.size.x), 
-    model.tex_coords.y + (entity.index.y * entity.size.y));
-    out.clip_position = camera.proj_mat * model_mat * vec4<f32>(model.position, 1.0);
+    // Position within the texture cell, (0, 0) top left to (1, 1) bottom right
+    let local = model.tex_coords / entity.size;
+    let cell = entity.uv_rect.xy + local * entity.uv_rect.zw;
+    // Crop the quad to the source rectangle, quads are one unit across
+    let offset = cell - local;
+    let position = vec3<f32>(model.position.x + offset.x, model.position.y - offset.y, model.position.z);
+    let flipped = entity.uv_rect.xy + (1.0 - local) * entity.uv_rect.zw;
+    let sample = mix(cell, flipped, entity.flip);
+    out.tex_coords = (entity.index + sample) * entity.size;
+    out.tint = entity.tint;
+    out.clip_position = camera.proj_mat * model_mat * vec4<f32>(position, 1.0);
     return out;
 }
 
@@ -48,7 +60,49 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+fn shade(in: VertexOutput) -> vec4<f32> {
+    let colour = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // Textures are premultiplied, so opacity has to scale the colour too
+    let alpha = in.tint.a;
+    return vec4<f32>(colour.rgb * in.tint.rgb * alpha, colour.a * alpha);
+}
+
 @fragment
 fn frg_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return shade(in);
+}
+
+// Alpha at or above which a texel is opaque and may write depth
+const OPAQUE_ALPHA: f32 = 254.5 / 255.0;
+
+// Used for the depth pass, anything see through must not write depth
+// or it would hide sprites behind it that are drawn later
+@fragment
+fn frg_depth(in: VertexOutput) -> @location(0) vec4<f32> {
+    let colour = shade(in);
+    if colour.a < OPAQUE_ALPHA {
+        discard;
+    }
+    return colour;
+}
+
+// Straight colour for BlendMode::Alpha, textures are premultiplied when loaded
+fn shade_straight(in: VertexOutput) -> vec4<f32> {
+    let colour = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let rgb = colour.rgb / max(colour.a, 0.0001);
+    return vec4<f32>(rgb * in.tint.rgb, colour.a * in.tint.a);
+}
+
+@fragment
+fn frg_straight(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade_straight(in);
+}
+
+@fragment
+fn frg_straight_depth(in: VertexOutput) -> @location(0) vec4<f32> {
+    let colour = shade_straight(in);
+    if colour.a < OPAQUE_ALPHA {
+        discard;
+    }
+    return colour;
 }
@@ -0,0 +1,25 @@
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vrt_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    // One triangle large enough to cover the whole viewport, no buffers needed
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    out.tex_coords = uv;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@group(0) @binding(0)
+var t_virtual: texture_2d<f32>;
+@group(0) @binding(1)
+var s_virtual: sampler;
+
+@fragment
+fn frg_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_virtual, s_virtual, in.tex_coords);
+}
@@ -0,0 +1,395 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use image::{Rgba, RgbaImage};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    engine::{
+        camera::camera::{Camera2DSystem, CameraProjection},
+        entity::entity::{Entity2D, EntitySystem2D},
+        layer::layer::{Layer2D, LayerID},
+        primitives::vector::Vector3,
+        texture::texture2d::{Texture2D, TextureID},
+        util::effect_error::EffectError,
+    },
+    EffectSystem,
+};
+
+/// Set this environment variable to write rendered frames over the stored references
+/// rather than comparing against them.
+pub const UPDATE_GOLDEN_VAR: &str = "EFFECT_UPDATE_GOLDEN";
+
+type EntitySetup = Box<dyn Fn(&mut Entity2D, &Layer2D)>;
+
+pub struct GoldenEntity {
+    texture: TextureID,
+    position: Vector3<f32>,
+    rotation: f32,
+    scale: f32,
+    setup: Option<EntitySetup>,
+}
+
+impl GoldenEntity {
+    pub fn new(texture: TextureID, position: Vector3<f32>) -> Self {
+        Self {
+            texture,
+            position,
+            rotation: 0.0,
+            scale: 1.0,
+            setup: None,
+        }
+    }
+
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Runs after the entity is created, for any property not covered above.
+    pub fn with_setup(mut self, setup: impl Fn(&mut Entity2D, &Layer2D) + 'static) -> Self {
+        self.setup = Some(Box::new(setup));
+        self
+    }
+}
+
+pub struct GoldenLayer {
+    id: LayerID,
+    textures: Vec<Texture2D>,
+    texture_size: PhysicalSize<u32>,
+    pixel_art: bool,
+    entities: Vec<GoldenEntity>,
+}
+
+impl GoldenLayer {
+    pub fn new(
+        id: LayerID,
+        textures: Vec<Texture2D>,
+        texture_size: PhysicalSize<u32>,
+        pixel_art: bool,
+    ) -> Self {
+        Self {
+            id,
+            textures,
+            texture_size,
+            pixel_art,
+            entities: Vec::new(),
+        }
+    }
+
+    pub fn with_entity(mut self, entity: GoldenEntity) -> Self {
+        self.entities.push(entity);
+        self
+    }
+}
+
+/// A description of everything needed to render one frame.
+/// Layers are rendered in the order they were added.
+pub struct GoldenScene {
+    dimensions: PhysicalSize<u32>,
+    background: Option<(Texture2D, bool)>,
+    layers: Vec<GoldenLayer>,
+    camera_position: Vector3<f32>,
+    camera_projection: CameraProjection,
+}
+
+impl GoldenScene {
+    pub fn new(dimensions: PhysicalSize<u32>) -> Self {
+        Self {
+            dimensions,
+            background: None,
+            layers: Vec::new(),
+            camera_position: Vector3::new(0.0, 0.0, 1.0),
+            camera_projection: CameraProjection::Perspective { fov_deg: 45.0 },
+        }
+    }
+
+    pub fn with_background(mut self, texture: Texture2D, pixel_art: bool) -> Self {
+        self.background = Some((texture, pixel_art));
+        self
+    }
+
+    pub fn with_layer(mut self, layer: GoldenLayer) -> Self {
+        self.layers.push(layer);
+        self
+    }
+
+    pub fn with_camera(mut self, position: Vector3<f32>, fov: f32) -> Self {
+        self.camera_position = position;
+        self.camera_projection = CameraProjection::Perspective { fov_deg: fov };
+        self
+    }
+
+    pub fn with_camera_projection(
+        mut self,
+        position: Vector3<f32>,
+        projection: CameraProjection,
+    ) -> Self {
+        self.camera_position = position;
+        self.camera_projection = projection;
+        self
+    }
+}
+
+pub struct GoldenComparison {
+    /// Number of pixels where any channel differs by more than the tolerance
+    pub mismatched_pixels: usize,
+    /// Largest difference found in any channel
+    pub max_difference: u8,
+    /// Mismatched pixels in red over a faded copy of the rendered frame
+    pub diff: RgbaImage,
+}
+
+impl GoldenComparison {
+    pub fn passed(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+pub struct GoldenSystem;
+
+impl GoldenSystem {
+    /// Renders the scene offscreen through the regular engine pipeline.
+    pub fn render(scene: &GoldenScene) -> Result<RgbaImage> {
+        let mut app = EffectSystem::new_headless(scene.dimensions)?;
+        if let Some((texture, pixel_art)) = scene.background.as_ref() {
+            app.set_background(texture.clone(), *pixel_art)?;
+        }
+        let mut layers = Vec::new();
+        for golden_layer in scene.layers.iter() {
+            let mut layer = app.init_layer(
+                golden_layer.id,
+                golden_layer.textures.clone(),
+                golden_layer.texture_size,
+                golden_layer.pixel_art,
+            )?;
+            let mut entities = Vec::new();
+            for golden_entity in golden_layer.entities.iter() {
+                if !layer.contains_texture(&golden_entity.texture) {
+                    bail!(EffectError::new(
+                        "Golden entity texture is not in its layer"
+                    ));
+                }
+                let mut entity =
+                    Entity2D::new(golden_entity.position, &layer, golden_entity.texture);
+                EntitySystem2D::set_scale(&mut entity, golden_entity.scale);
+                EntitySystem2D::set_rotation(&mut entity, golden_entity.rotation);
+                if let Some(setup) = golden_entity.setup.as_ref() {
+                    setup(&mut entity, &layer);
+                }
+                entities.push(entity);
+            }
+            let entity_refs = entities.iter().collect::<Vec<_>>();
+            app.set_entities(&mut layer, &entity_refs);
+            layers.push(layer);
+        }
+        let mut camera = app.init_camera_with_projection(scene.camera_projection);
+        Camera2DSystem::transform(&mut camera, scene.camera_position);
+        app.update_camera(&mut camera);
+        app.render_to_image(&layers, &camera)
+    }
+
+    /// Compares two frames of the same size. A pixel mismatches when any of its
+    /// channels differ by more than `tolerance`.
+    pub fn compare(
+        actual: &RgbaImage,
+        reference: &RgbaImage,
+        tolerance: u8,
+    ) -> Result<GoldenComparison> {
+        if actual.dimensions() != reference.dimensions() {
+            bail!(EffectError::new(
+                "Rendered frame and reference have different dimensions"
+            ));
+        }
+        let mut mismatched_pixels = 0;
+        let mut max_difference = 0;
+        let mut diff = RgbaImage::new(actual.width(), actual.height());
+        for ((actual_pixel, reference_pixel), diff_pixel) in actual
+            .pixels()
+            .zip(reference.pixels())
+            .zip(diff.pixels_mut())
+        {
+            let difference = actual_pixel
+                .0
+                .iter()
+                .zip(reference_pixel.0.iter())
+                .map(|(a, r)| a.abs_diff(*r))
+                .max()
+                .unwrap_or(0);
+            max_difference = max_difference.max(difference);
+            if difference > tolerance {
+                mismatched_pixels += 1;
+                *diff_pixel = Rgba([255, 0, 0, 255]);
+            } else {
+                let [r, g, b, _] = actual_pixel.0;
+                let luma = ((r as u32 + g as u32 + b as u32) / 3 / 4) as u8;
+                *diff_pixel = Rgba([luma, luma, luma, 255]);
+            }
+        }
+        Ok(GoldenComparison {
+            mismatched_pixels,
+            max_difference,
+            diff,
+        })
+    }
+
+    /// Renders the scene and compares it with the reference PNG.
+    /// On failure the rendered frame and a diff image are written next to the
+    /// reference as `<name>.actual.png` and `<name>.diff.png`.
+    /// When `EFFECT_UPDATE_GOLDEN` is set the reference is overwritten instead.
+    pub fn check(scene: &GoldenScene, reference: impl AsRef<Path>, tolerance: u8) -> Result<()> {
+        let reference = reference.as_ref();
+        let actual = GoldenSystem::render(scene)?;
+        if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+            if let Some(parent) = reference.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            actual.save(reference)?;
+            return Ok(());
+        }
+        let actual_path = GoldenSystem::sibling_path(reference, "actual");
+        let expected = match image::open(reference) {
+            Ok(expected) => expected.into_rgba8(),
+            Err(_) => {
+                actual.save(&actual_path)?;
+                bail!(EffectError::new(&format!(
+                    "Reference {} could not be read, set {UPDATE_GOLDEN_VAR} to create it",
+                    reference.display()
+                )));
+            }
+        };
+        if expected.dimensions() != actual.dimensions() {
+            actual.save(&actual_path)?;
+            bail!(EffectError::new(&format!(
+                "Rendered frame is {:?} but reference {} is {:?}",
+                actual.dimensions(),
+                reference.display(),
+                expected.dimensions()
+            )));
+        }
+        let comparison = GoldenSystem::compare(&actual, &expected, tolerance)?;
+        if !comparison.passed() {
+            let diff_path = GoldenSystem::sibling_path(reference, "diff");
+            actual.save(&actual_path)?;
+            comparison.diff.save(&diff_path)?;
+            bail!(EffectError::new(&format!(
+                "{} pixels differ from {} by more than {tolerance} (max {}), see {}",
+                comparison.mismatched_pixels,
+                reference.display(),
+                comparison.max_difference,
+                diff_path.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn sibling_path(reference: &Path, suffix: &str) -> PathBuf {
+        let stem = reference
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        reference.with_file_name(format!("{stem}.{suffix}.png"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::util::effect_error::EngineError;
+
+    fn solid(width: u32, height: u32, colour: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba(colour))
+    }
+
+    #[test]
+    fn compare_identical_frames_passes() {
+        let frame = solid(4, 4, [10, 20, 30, 255]);
+        let comparison = GoldenSystem::compare(&frame, &frame, 0).unwrap();
+        assert!(comparison.passed());
+        assert_eq!(comparison.max_difference, 0);
+    }
+
+    #[test]
+    fn compare_counts_pixels_beyond_tolerance() {
+        let reference = solid(4, 4, [100, 100, 100, 255]);
+        let mut actual = reference.clone();
+        actual.put_pixel(0, 0, Rgba([103, 100, 100, 255]));
+        actual.put_pixel(1, 0, Rgba([100, 110, 100, 255]));
+        actual.put_pixel(2, 0, Rgba([100, 100, 100, 200]));
+
+        let comparison = GoldenSystem::compare(&actual, &reference, 3).unwrap();
+        assert!(!comparison.passed());
+        assert_eq!(comparison.mismatched_pixels, 2);
+        assert_eq!(comparison.max_difference, 55);
+
+        let lenient = GoldenSystem::compare(&actual, &reference, 55).unwrap();
+        assert!(lenient.passed());
+    }
+
+    #[test]
+    fn compare_marks_mismatches_red_over_faded_frame() {
+        let reference = solid(2, 1, [120, 120, 120, 255]);
+        let mut actual = reference.clone();
+        actual.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        let comparison = GoldenSystem::compare(&actual, &reference, 0).unwrap();
+        assert_eq!(*comparison.diff.get_pixel(0, 0), Rgba([30, 30, 30, 255]));
+        assert_eq!(*comparison.diff.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn compare_rejects_different_dimensions() {
+        let actual = solid(2, 2, [0, 0, 0, 255]);
+        let reference = solid(2, 3, [0, 0, 0, 255]);
+        assert!(GoldenSystem::compare(&actual, &reference, 0).is_err());
+    }
+
+    #[test]
+    fn sibling_path_keeps_directory_and_stem() {
+        let path = GoldenSystem::sibling_path(Path::new("tests/golden/scene.png"), "diff");
+        assert_eq!(path, Path::new("tests/golden/scene.diff.png"));
+    }
+
+    #[test]
+    fn golden_sprites_scene() {
+        // Rendering needs an adapter, which some CI machines don't have at all
+        if let Err(EngineError::NoAdapter) = EffectSystem::new_headless(PhysicalSize::new(1, 1)) {
+            eprintln!("No adapter available, skipping golden scene");
+            return;
+        }
+        let scene = GoldenScene::new(PhysicalSize::new(160, 80))
+            .with_background(
+                Texture2D::new(TextureID("grass"), "grass_bg_small.png"),
+                true,
+            )
+            .with_layer(
+                GoldenLayer::new(
+                    LayerID(0),
+                    vec![
+                        Texture2D::new(TextureID("tree"), "tree.png"),
+                        Texture2D::new(TextureID("evil"), "evil.png"),
+                    ],
+                    PhysicalSize::new(32, 32),
+                    true,
+                )
+                .with_entity(GoldenEntity::new(
+                    TextureID("tree"),
+                    Vector3::new(-0.4, 0.0, 0.0),
+                ))
+                .with_entity(
+                    GoldenEntity::new(TextureID("evil"), Vector3::new(0.4, 0.0, 0.0))
+                        .with_rotation(30.0)
+                        .with_scale(0.5),
+                ),
+            )
+            .with_camera_projection(
+                Vector3::new(0.0, 0.0, 1.0),
+                CameraProjection::Orthographic { world_height: 1.0 },
+            );
+        GoldenSystem::check(&scene, "tests/golden/sprites.png", 8).unwrap();
+    }
+}